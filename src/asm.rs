@@ -0,0 +1,429 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Assembler for CHIP-8 programs. Accepts the same
+ *                  mnemonics as printed by the disassembler, and
+ *                  emits a binary to be loaded at PROGRAM_START.
+ *
+ *    Syntax:
+ *        ; Comments start with a semicolon
+ *        SPEED   EQU 3           ; Constants
+ *        loop:   ADD V0, SPEED   ; Labels, optionally followed by code
+ *                JP loop
+ *        sprite: DB "#..##..#"   ; Sprite rows, '#' is a lit pixel
+ *                DB 0xFF, %10000001, 255
+ *                DW 0x1234, loop
+ *                LD I, LONG sprite ; XO-CHIP four byte load
+ *
+ *    Numbers may be written in decimal, hex (0x1F or $1F)
+ *    or binary (0b1010 or %1010).
+ */
+use crate::cpu::{PROGRAM_START, XO_CHIP_RAM_SIZE};
+use crate::instruction::Instruction;
+use std::collections::HashMap;
+use std::fmt;
+
+// Largest program that fits in RAM after the program start,
+// in XO-CHIP mode
+const MAX_PROGRAM_SIZE: usize = XO_CHIP_RAM_SIZE - PROGRAM_START as usize;
+
+// Maximum nesting of constants referring to other constants
+const MAX_SYMBOL_DEPTH: usize = 16;
+
+// Raised when a program fails to assemble
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    /// Source line the error was found on, starting from 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// A statement that emits bytes into the program
+enum Statement {
+    Inst {
+        mnemonic: String,
+        operands: Vec<String>,
+    },
+    Bytes(Vec<String>),
+    Words(Vec<String>),
+}
+
+// The kinds of operands that instructions take
+enum Operand<'a> {
+    V(u8),
+    I,
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    Hf,
+    B,
+    R,
+    Value(&'a str),
+}
+
+fn parse_operand(op: &str) -> Operand<'_> {
+    match op.to_ascii_uppercase().as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "DT" => Operand::Dt,
+        "ST" => Operand::St,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "HF" => Operand::Hf,
+        "B" => Operand::B,
+        "R" => Operand::R,
+        reg if reg.len() == 2 && reg.starts_with('V') => match u8::from_str_radix(&reg[1..], 16) {
+            Ok(x) => Operand::V(x),
+            Err(..) => Operand::Value(op),
+        },
+        _ => Operand::Value(op),
+    }
+}
+
+// Parses a number literal in decimal, hex or binary
+fn parse_number(s: &str) -> Option<u16> {
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u16::from_str_radix(hex, 16).ok()
+    } else if let Some(hex) = s.strip_prefix('$') {
+        u16::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = s.strip_prefix("0b").or_else(|| s.strip_prefix("0B")) {
+        u16::from_str_radix(bin, 2).ok()
+    } else if let Some(bin) = s.strip_prefix('%') {
+        u16::from_str_radix(bin, 2).ok()
+    } else {
+        s.parse::<u16>().ok()
+    }
+}
+
+// Parses a quoted sprite row such as "#..##..#" into a value
+// of the given bit width. Rows are left aligned, so that
+// the first character is the most significant bit
+fn parse_sprite(s: &str, width: usize) -> Option<u16> {
+    let row = s.strip_prefix('"')?.strip_suffix('"')?;
+    if row.len() > width {
+        return None;
+    }
+    let mut val = 0_u16;
+    for (i, c) in row.chars().enumerate() {
+        match c {
+            '#' | '1' => val |= 1 << (width - 1 - i),
+            '.' | ' ' | '0' => {}
+            _ => return None,
+        }
+    }
+    Some(val)
+}
+
+// Splits a comma separated operand list, keeping quoted strings intact
+fn split_operands(s: &str) -> Vec<String> {
+    let mut operands = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in s.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            ',' if !quoted => {
+                operands.push(current.trim().to_string());
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.trim().is_empty() || !operands.is_empty() {
+        operands.push(current.trim().to_string());
+    }
+    operands
+}
+
+// Removes a trailing comment, ignoring semicolons in quoted strings
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+// Strips the LONG keyword from the operand of the
+// four byte XO-CHIP instruction LD I, LONG nnnn
+fn long_operand(op: &str) -> Option<&str> {
+    let (keyword, rest) = op.split_once(char::is_whitespace)?;
+    if keyword.eq_ignore_ascii_case("LONG") {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+fn is_symbol(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+// Labels and constants defined in the program
+#[derive(Default)]
+struct Symbols {
+    labels: HashMap<String, u16>,
+    constants: HashMap<String, String>,
+}
+
+impl Symbols {
+    fn is_defined(&self, name: &str) -> bool {
+        self.labels.contains_key(name) || self.constants.contains_key(name)
+    }
+
+    // Evaluates an operand that is a number, label or constant
+    fn value(&self, s: &str, width: usize, depth: usize) -> Result<u16, String> {
+        if let Some(val) = parse_number(s) {
+            return Ok(val);
+        }
+        if s.starts_with('"') {
+            return parse_sprite(s, width).ok_or(format!("Invalid sprite row {}", s));
+        }
+        if let Some(&addr) = self.labels.get(s) {
+            return Ok(addr);
+        }
+        match self.constants.get(s) {
+            Some(_) if depth >= MAX_SYMBOL_DEPTH => {
+                Err(format!("Constant {} is defined in terms of itself", s))
+            }
+            Some(val) => self.value(val, width, depth + 1),
+            None => Err(format!("Undefined symbol {}", s)),
+        }
+    }
+
+    // Evaluates an operand that must fit in the given number of bits
+    fn bits(&self, s: &str, bits: usize) -> Result<u16, String> {
+        let val = self.value(s, bits, 0)?;
+        if val as u32 >= 1 << bits {
+            return Err(format!("Value {} does not fit in {} bits", s, bits));
+        }
+        Ok(val)
+    }
+
+    fn addr(&self, s: &str) -> Result<u16, String> {
+        self.bits(s, 12)
+    }
+
+    fn byte(&self, s: &str) -> Result<u8, String> {
+        Ok(self.bits(s, 8)? as u8)
+    }
+
+    fn nibble(&self, s: &str) -> Result<u8, String> {
+        Ok(self.bits(s, 4)? as u8)
+    }
+
+    // Resolves an instruction mnemonic and its operands
+    fn instruction(&self, mnemonic: &str, operands: &[String]) -> Result<Instruction, String> {
+        use Operand::*;
+        let ops: Vec<Operand> = operands.iter().map(|op| parse_operand(op)).collect();
+        let inst = match (mnemonic.to_ascii_uppercase().as_str(), ops.as_slice()) {
+            ("CLS", []) => Instruction::Cls,
+            ("RET", []) => Instruction::Ret,
+            ("SYS", [Value(a)]) => Instruction::Sys(self.addr(a)?),
+            ("JP", [Value(a)]) => Instruction::Jp(self.addr(a)?),
+            ("JP", [V(0), Value(a)]) => Instruction::JpV0(self.addr(a)?),
+            ("CALL", [Value(a)]) => Instruction::Call(self.addr(a)?),
+            ("SE", [V(x), V(y)]) => Instruction::SeReg { x: *x, y: *y },
+            ("SE", [V(x), Value(kk)]) => Instruction::SeByte {
+                x: *x,
+                kk: self.byte(kk)?,
+            },
+            ("SNE", [V(x), V(y)]) => Instruction::SneReg { x: *x, y: *y },
+            ("SNE", [V(x), Value(kk)]) => Instruction::SneByte {
+                x: *x,
+                kk: self.byte(kk)?,
+            },
+            ("LD", [V(x), V(y)]) => Instruction::LdReg { x: *x, y: *y },
+            ("LD", [V(x), Value(kk)]) => Instruction::LdByte {
+                x: *x,
+                kk: self.byte(kk)?,
+            },
+            ("LD", [I, Value(a)]) => match long_operand(a) {
+                Some(a) => Instruction::LdILong(self.bits(a, 16)?),
+                None => Instruction::LdI(self.addr(a)?),
+            },
+            ("LD", [V(x), Dt]) => Instruction::LdVxDt { x: *x },
+            ("LD", [V(x), K]) => Instruction::LdKey { x: *x },
+            ("LD", [Dt, V(x)]) => Instruction::LdDtVx { x: *x },
+            ("LD", [St, V(x)]) => Instruction::LdStVx { x: *x },
+            ("LD", [F, V(x)]) => Instruction::LdFont { x: *x },
+            ("LD", [B, V(x)]) => Instruction::LdBcd { x: *x },
+            ("LD", [IndirectI, V(x)]) => Instruction::StoreRegs { x: *x },
+            ("LD", [V(x), IndirectI]) => Instruction::LoadRegs { x: *x },
+            ("ADD", [V(x), V(y)]) => Instruction::AddReg { x: *x, y: *y },
+            ("ADD", [V(x), Value(kk)]) => Instruction::AddByte {
+                x: *x,
+                kk: self.byte(kk)?,
+            },
+            ("ADD", [I, V(x)]) => Instruction::AddI { x: *x },
+            ("OR", [V(x), V(y)]) => Instruction::Or { x: *x, y: *y },
+            ("AND", [V(x), V(y)]) => Instruction::And { x: *x, y: *y },
+            ("XOR", [V(x), V(y)]) => Instruction::Xor { x: *x, y: *y },
+            ("SUB", [V(x), V(y)]) => Instruction::Sub { x: *x, y: *y },
+            ("SUBN", [V(x), V(y)]) => Instruction::Subn { x: *x, y: *y },
+            ("SHR", [V(x)]) => Instruction::Shr { x: *x, y: 0 },
+            ("SHR", [V(x), V(y)]) => Instruction::Shr { x: *x, y: *y },
+            ("SHL", [V(x)]) => Instruction::Shl { x: *x, y: 0 },
+            ("SHL", [V(x), V(y)]) => Instruction::Shl { x: *x, y: *y },
+            ("RND", [V(x), Value(kk)]) => Instruction::Rnd {
+                x: *x,
+                kk: self.byte(kk)?,
+            },
+            ("DRW", [V(x), V(y), Value(n)]) => Instruction::Drw {
+                x: *x,
+                y: *y,
+                n: self.nibble(n)?,
+            },
+            ("SKP", [V(x)]) => Instruction::Skp { x: *x },
+            ("SKNP", [V(x)]) => Instruction::Sknp { x: *x },
+            ("SCD", [Value(n)]) => Instruction::ScrollDown(self.nibble(n)?),
+            ("SCU", [Value(n)]) => Instruction::ScrollUp(self.nibble(n)?),
+            ("SCR", []) => Instruction::ScrollRight,
+            ("SCL", []) => Instruction::ScrollLeft,
+            ("EXIT", []) => Instruction::Exit,
+            ("LOW", []) => Instruction::Lores,
+            ("HIGH", []) => Instruction::Hires,
+            ("LD", [Hf, V(x)]) => Instruction::LdHiFont { x: *x },
+            ("LD", [R, V(x)]) => Instruction::StoreFlags { x: *x },
+            ("LD", [V(x), R]) => Instruction::LoadFlags { x: *x },
+            ("PLANE", [Value(n)]) => Instruction::Plane(self.bits(n, 2)? as u8),
+            ("SAVE", [V(x), V(y)]) => Instruction::SaveRange { x: *x, y: *y },
+            ("LOAD", [V(x), V(y)]) => Instruction::LoadRange { x: *x, y: *y },
+            ("AUDIO", []) => Instruction::LdAudio,
+            ("PITCH", [V(x)]) => Instruction::LdPitch { x: *x },
+            _ => {
+                return Err(format!(
+                    "Invalid instruction {} {}",
+                    mnemonic,
+                    operands.join(", ")
+                ))
+            }
+        };
+        Ok(inst)
+    }
+}
+
+// Assembles a program into a binary that is loaded at PROGRAM_START
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut symbols = Symbols::default();
+    let mut statements = vec![];
+
+    // First pass, collects all symbols and the statements that
+    // emit bytes, keeping track of the address of each statement
+    let mut addr = PROGRAM_START as usize;
+    for (idx, line) in source.lines().enumerate() {
+        let line_no = idx + 1;
+        let err = |message: String| AsmError {
+            line: line_no,
+            message,
+        };
+        let mut rest = strip_comment(line).trim();
+        if rest.is_empty() {
+            continue;
+        }
+
+        // Constant definition, NAME EQU value
+        let mut words = rest.split_whitespace();
+        if let (Some(name), Some(equ)) = (words.next(), words.next()) {
+            if equ.eq_ignore_ascii_case("EQU") {
+                if !is_symbol(name) || symbols.is_defined(name) {
+                    return Err(err(format!("Invalid or duplicate constant {}", name)));
+                }
+                let val = words.collect::<Vec<&str>>().join(" ");
+                symbols.constants.insert(name.to_string(), val);
+                continue;
+            }
+        }
+
+        // Label, optionally followed by a statement on the same line
+        if let Some(colon) = rest.find(':') {
+            let name = rest[..colon].trim();
+            if is_symbol(name) {
+                if symbols.is_defined(name) {
+                    return Err(err(format!("Duplicate label {}", name)));
+                }
+                symbols.labels.insert(name.to_string(), addr as u16);
+                rest = rest[colon + 1..].trim();
+                if rest.is_empty() {
+                    continue;
+                }
+            }
+        }
+
+        let (mnemonic, operands) = match rest.find(char::is_whitespace) {
+            Some(i) => (&rest[..i], split_operands(&rest[i..])),
+            None => (rest, vec![]),
+        };
+        let statement = match mnemonic.to_ascii_uppercase().as_str() {
+            "DB" => Statement::Bytes(operands),
+            "DW" => Statement::Words(operands),
+            _ => Statement::Inst {
+                mnemonic: mnemonic.to_string(),
+                operands,
+            },
+        };
+        addr += match &statement {
+            Statement::Inst { operands, .. } => match operands.get(1) {
+                Some(op) if long_operand(op).is_some() => 4,
+                _ => 2,
+            },
+            Statement::Bytes(ops) => ops.len(),
+            Statement::Words(ops) => 2 * ops.len(),
+        };
+        if addr > PROGRAM_START as usize + MAX_PROGRAM_SIZE {
+            return Err(err("Program does not fit in RAM".to_string()));
+        }
+        statements.push((line_no, statement));
+    }
+
+    // Second pass, emits the bytes with all symbols resolved
+    let mut program = vec![];
+    for (line_no, statement) in statements {
+        let err = |message: String| AsmError {
+            line: line_no,
+            message,
+        };
+        match statement {
+            Statement::Inst { mnemonic, operands } => {
+                let inst = symbols.instruction(&mnemonic, &operands).map_err(err)?;
+                program.extend_from_slice(&inst.to_bytes());
+            }
+            Statement::Bytes(operands) => {
+                for op in operands {
+                    program.push(symbols.byte(&op).map_err(err)?);
+                }
+            }
+            Statement::Words(operands) => {
+                for op in operands {
+                    program.extend_from_slice(&symbols.bits(&op, 16).map_err(err)?.to_be_bytes());
+                }
+            }
+        }
+    }
+    Ok(program)
+}
@@ -0,0 +1,242 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - The buzzer, which sounds while the sound timer is
+ *                  active. Audio is rendered cycle by cycle from the
+ *                  CPU state to an output, such as the sound device of
+ *                  the frontend, or a WAV file. XO-CHIP programs that load
+ *                  an audio pattern play it back instead of the tone.
+ */
+use crate::cpu::Cpu;
+use std::f64::consts::TAU;
+use std::fs::File;
+use std::io::{self, BufWriter};
+
+// Sample rate used unless the output asks for another one
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+// Rate of the CPU frames, and of the timers
+const FRAME_RATE: f64 = 60.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 3] = [Waveform::Square, Waveform::Sine, Waveform::Triangle];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Waveform::Square => "Square",
+            Waveform::Sine => "Sine",
+            Waveform::Triangle => "Triangle",
+        }
+    }
+
+    // Returns the value of the wave, between -1 and 1, at a phase
+    // between 0 and 1
+    pub fn sample(&self, phase: f64) -> f64 {
+        match self {
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BuzzerSettings {
+    pub waveform: Waveform,
+    /// Frequency of the tone in Hz
+    pub frequency: f64,
+    /// Volume between 0 and 1
+    pub volume: f64,
+    pub muted: bool,
+}
+
+impl Default for BuzzerSettings {
+    fn default() -> Self {
+        BuzzerSettings {
+            waveform: Waveform::Square,
+            frequency: 440.0,
+            volume: 0.25,
+            muted: false,
+        }
+    }
+}
+
+// Somewhere to send the rendered audio, as mono samples between -1 and 1
+pub trait AudioOutput {
+    fn write(&mut self, samples: &[f32]);
+}
+
+// Collects the samples in memory
+impl AudioOutput for Vec<f32> {
+    fn write(&mut self, samples: &[f32]) {
+        self.extend_from_slice(samples);
+    }
+}
+
+// Discards the samples, for frontends without a sound device
+pub struct NullOutput;
+
+impl AudioOutput for NullOutput {
+    fn write(&mut self, _samples: &[f32]) {}
+}
+
+// Sends the samples to two outputs, such as the sound device and a recording
+pub struct Tee<'a>(pub &'a mut dyn AudioOutput, pub &'a mut dyn AudioOutput);
+
+impl AudioOutput for Tee<'_> {
+    fn write(&mut self, samples: &[f32]) {
+        self.0.write(samples);
+        self.1.write(samples);
+    }
+}
+
+// Writes the samples to a 16-bit mono WAV file
+pub struct WavOutput {
+    writer: hound::WavWriter<BufWriter<File>>,
+    /// First error writing the file. Writing stops after an error
+    error: Option<io::Error>,
+}
+
+impl WavOutput {
+    pub fn create(path: &str, sample_rate: u32) -> io::Result<Self> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::create(path, spec).map_err(wav_error)?;
+        Ok(WavOutput {
+            writer,
+            error: None,
+        })
+    }
+
+    // Completes the header of the file, returning the first error writing it
+    pub fn finish(self) -> io::Result<()> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        self.writer.finalize().map_err(wav_error)
+    }
+}
+
+impl AudioOutput for WavOutput {
+    fn write(&mut self, samples: &[f32]) {
+        if self.error.is_some() {
+            return;
+        }
+        for &sample in samples {
+            let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            if let Err(e) = self.writer.write_sample(sample) {
+                self.error = Some(wav_error(e));
+                return;
+            }
+        }
+    }
+}
+
+fn wav_error(e: hound::Error) -> io::Error {
+    match e {
+        hound::Error::IoError(e) => e,
+        e => io::Error::other(e.to_string()),
+    }
+}
+
+// Number of 1-bit samples in the XO-CHIP audio pattern
+const PATTERN_BITS: usize = 128;
+
+// Returns the value, 1 or -1, of the bit of the audio pattern
+// at a phase between 0 and 1 through it
+fn pattern_sample(pattern: &[u8; 16], phase: f64) -> f64 {
+    let bit = (phase * PATTERN_BITS as f64) as usize % PATTERN_BITS;
+    if pattern[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+pub struct Buzzer {
+    pub settings: BuzzerSettings,
+    sample_rate: u32,
+    /// Position in the period of the wave, or in the XO-CHIP
+    /// audio pattern, between 0 and 1
+    phase: f64,
+    /// Fraction of a sample carried over from the previous cycle
+    owed: f64,
+    /// Samples rendered since the last flush
+    samples: Vec<f32>,
+}
+
+impl Buzzer {
+    pub fn new(settings: BuzzerSettings, sample_rate: u32) -> Self {
+        Buzzer {
+            settings,
+            sample_rate,
+            phase: 0.0,
+            owed: 0.0,
+            samples: vec![],
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // Renders the audio of the CPU cycle that was just run. Every frame
+    // of cycles lasts 1/60 s of emulated time, like the timers, so the
+    // audio keeps in step with the emulation at any clock speed
+    pub fn cycle(&mut self, cpu: &Cpu) {
+        let per_cycle = self.sample_rate as f64 / (FRAME_RATE * cpu.cycles_per_frame() as f64);
+        self.owed += per_cycle;
+        let count = self.owed as usize;
+        self.owed -= count as f64;
+        let active = cpu.timers().sound_active() && !self.settings.muted;
+        if !active {
+            // Restarting the wave from the beginning avoids clicks
+            self.phase = 0.0;
+            self.samples.extend(std::iter::repeat_n(0.0, count));
+            return;
+        }
+        let pattern = cpu.audio_pattern();
+        // An empty pattern means the program never loaded one,
+        // so it gets the plain tone like any other CHIP-8 program
+        let use_pattern = cpu.is_xo_chip() && pattern.iter().any(|&byte| byte != 0);
+        let step = if use_pattern {
+            cpu.audio_pattern_rate() / (PATTERN_BITS as f64 * self.sample_rate as f64)
+        } else {
+            self.settings.frequency / self.sample_rate as f64
+        };
+        for _ in 0..count {
+            let value = if use_pattern {
+                pattern_sample(pattern, self.phase)
+            } else {
+                self.settings.waveform.sample(self.phase)
+            };
+            self.samples.push((value * self.settings.volume) as f32);
+            self.phase = (self.phase + step).fract();
+        }
+    }
+
+    // Sends the samples rendered so far to the output
+    pub fn flush(&mut self, output: &mut dyn AudioOutput) {
+        output.write(&self.samples);
+        self.samples.clear();
+    }
+}
@@ -0,0 +1,293 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Breakpoints and watchpoints that stop the CPU, with
+ *                  optional conditions on the registers.
+ */
+use crate::cpu::Cpu;
+use std::fmt;
+use std::str::FromStr;
+
+// A register that conditions can test
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+    Pc,
+    Sp,
+    Dt,
+    St,
+}
+
+impl Register {
+    // Returns the current value of the register
+    pub fn value(&self, cpu: &Cpu) -> u16 {
+        match *self {
+            Register::V(x) => cpu.registers()[x as usize] as u16,
+            Register::I => cpu.i(),
+            Register::Pc => cpu.pc(),
+            Register::Sp => cpu.stack().len() as u16,
+            Register::Dt => cpu.timers().delay() as u16,
+            Register::St => cpu.timers().sound() as u16,
+        }
+    }
+}
+
+impl FromStr for Register {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_ascii_uppercase();
+        match upper.as_str() {
+            "I" => Ok(Register::I),
+            "PC" => Ok(Register::Pc),
+            "SP" => Ok(Register::Sp),
+            "DT" => Ok(Register::Dt),
+            "ST" => Ok(Register::St),
+            _ => upper
+                .strip_prefix('V')
+                .filter(|x| x.len() == 1)
+                .and_then(|x| u8::from_str_radix(x, 16).ok())
+                .map(Register::V)
+                .ok_or(format!("Unknown register {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{:X}", x),
+            Register::I => write!(f, "I"),
+            Register::Pc => write!(f, "PC"),
+            Register::Sp => write!(f, "SP"),
+            Register::Dt => write!(f, "DT"),
+            Register::St => write!(f, "ST"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    const ALL: [(&'static str, Comparison); 6] = [
+        ("==", Comparison::Eq),
+        ("!=", Comparison::Ne),
+        ("<=", Comparison::Le),
+        (">=", Comparison::Ge),
+        ("<", Comparison::Lt),
+        (">", Comparison::Gt),
+    ];
+
+    fn symbol(&self) -> &'static str {
+        Comparison::ALL.iter().find(|(_, c)| c == self).unwrap().0
+    }
+}
+
+// Parses a decimal value, or a hex value with a 0x prefix
+fn parse_value(value: &str) -> Result<u16, String> {
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| format!("Invalid value {}", value))
+}
+
+// Parses an inclusive address range written as `start-end`, or a single address
+fn parse_range(range: &str) -> Result<(u16, u16), String> {
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (parse_value(start.trim())?, parse_value(end.trim())?),
+        None => {
+            let addr = parse_value(range)?;
+            (addr, addr)
+        }
+    };
+    if start > end {
+        return Err(format!("Invalid range {}", range));
+    }
+    Ok((start, end))
+}
+
+// A comparison of a register to a constant, e.g. `V3 == 0x10`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Condition {
+    pub register: Register,
+    pub comparison: Comparison,
+    pub value: u16,
+}
+
+impl Condition {
+    // Returns true if the condition holds for the current CPU state
+    pub fn holds(&self, cpu: &Cpu) -> bool {
+        let value = self.register.value(cpu);
+        match self.comparison {
+            Comparison::Eq => value == self.value,
+            Comparison::Ne => value != self.value,
+            Comparison::Lt => value < self.value,
+            Comparison::Le => value <= self.value,
+            Comparison::Gt => value > self.value,
+            Comparison::Ge => value >= self.value,
+        }
+    }
+}
+
+// Parses conditions written as `<register> <comparison> <value>`,
+// with the value in decimal or in hex with a 0x prefix
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (symbol, comparison, at) = Comparison::ALL
+            .iter()
+            .find_map(|&(symbol, comparison)| Some((symbol, comparison, s.find(symbol)?)))
+            .ok_or(format!("Expected a comparison in {}", s))?;
+        let register = s[..at].trim().parse()?;
+        let value = parse_value(s[at + symbol.len()..].trim())?;
+        Ok(Condition {
+            register,
+            comparison,
+            value,
+        })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {:#X}",
+            self.register,
+            self.comparison.symbol(),
+            self.value
+        )
+    }
+}
+
+// What a breakpoint reacts to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakKind {
+    /// Stops before the instruction at the address is executed
+    Exec(u16),
+    /// Stops after an instruction reads RAM in the inclusive range
+    Read { start: u16, end: u16 },
+    /// Stops after an instruction writes RAM in the inclusive range
+    Write { start: u16, end: u16 },
+    /// Stops after an instruction changes the value of Vx
+    Register(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub kind: BreakKind,
+    /// The breakpoint is only hit if the condition holds
+    pub condition: Option<Condition>,
+}
+
+impl Breakpoint {
+    pub fn new(kind: BreakKind) -> Self {
+        Breakpoint {
+            kind,
+            condition: None,
+        }
+    }
+
+    pub fn when(mut self, condition: Condition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            BreakKind::Exec(addr) => write!(f, "exec {:#05X}", addr)?,
+            BreakKind::Read { start, end } => write!(f, "read {:#05X}-{:#05X}", start, end)?,
+            BreakKind::Write { start, end } => write!(f, "write {:#05X}-{:#05X}", start, end)?,
+            BreakKind::Register(x) => write!(f, "change V{:X}", x)?,
+        }
+        match self.condition {
+            Some(condition) => write!(f, " if {}", condition),
+            None => Ok(()),
+        }
+    }
+}
+
+// Parses breakpoints written like they are displayed, e.g.
+// `exec 0x2A0`, `write 0x300-0x30F if I == 0x300` or `change VF if VF == 1`
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (s, condition) = match s.split_once(" if ") {
+            Some((s, condition)) => (s, Some(condition.parse()?)),
+            None => (s, None),
+        };
+        let (kind, arg) = s.trim().split_once(char::is_whitespace).ok_or(format!(
+            "Expected a breakpoint kind and an argument in {}",
+            s
+        ))?;
+        let arg = arg.trim();
+        let kind = match kind {
+            "exec" => BreakKind::Exec(parse_value(arg)?),
+            "read" => {
+                let (start, end) = parse_range(arg)?;
+                BreakKind::Read { start, end }
+            }
+            "write" => {
+                let (start, end) = parse_range(arg)?;
+                BreakKind::Write { start, end }
+            }
+            "change" => match arg.parse()? {
+                Register::V(x) => BreakKind::Register(x),
+                _ => return Err(format!("Only V registers can be watched, not {}", arg)),
+            },
+            _ => return Err(format!("Unknown breakpoint kind {}", kind)),
+        };
+        Ok(Breakpoint { kind, condition })
+    }
+}
+
+// Why the CPU stopped, reported by tick
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BreakReason {
+    /// Index of the breakpoint in Cpu::breakpoints
+    pub index: usize,
+    /// Address of the instruction that was stopped before or that triggered the break
+    pub pc: u16,
+    pub event: BreakEvent,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakEvent {
+    Exec,
+    Read { addr: u16 },
+    Write { addr: u16 },
+    Register { x: u8, old: u8, new: u8 },
+}
+
+impl fmt::Display for BreakReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Breakpoint {} at {:#05X}: ", self.index, self.pc)?;
+        match self.event {
+            BreakEvent::Exec => write!(f, "execution"),
+            BreakEvent::Read { addr } => write!(f, "read of {:#05X}", addr),
+            BreakEvent::Write { addr } => write!(f, "write to {:#05X}", addr),
+            BreakEvent::Register { x, old, new } => {
+                write!(f, "V{:X} changed from {:#04X} to {:#04X}", x, old, new)
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Settings of the frontend that are kept between runs,
+ *                  stored as a text file of `key = value` lines.
+ */
+use crate::palette::Palette;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    pub palette: Palette,
+}
+
+impl Config {
+    // Location of the config file, chip8/config in the user's
+    // config directory, or chip8.cfg in the working directory
+    // if there is no home directory
+    pub fn default_path() -> PathBuf {
+        let dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+        match dir {
+            Some(dir) => dir.join("chip8").join("config"),
+            None => PathBuf::from("chip8.cfg"),
+        }
+    }
+
+    // Writes the config to a file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_string())
+    }
+
+    // Reads the config from a file. A missing file gives the defaults
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(source) => source
+                .parse()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# CHIP-8 emulator settings")?;
+        writeln!(f, "palette = {}", self.palette)
+    }
+}
+
+// Parses the lines of a config file. Keys that are left out keep
+// their defaults, and lines starting with # are comments
+impl FromStr for Config {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = Config::default();
+        for (n, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {}", n + 1, message);
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("Expected key = value".to_string()))?;
+            match key.trim() {
+                "palette" => config.palette = value.parse().map_err(error)?,
+                key => return Err(error(format!("Unknown setting {}", key))),
+            }
+        }
+        Ok(config)
+    }
+}
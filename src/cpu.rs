@@ -5,37 +5,74 @@
  *    Project     - CHIP-8 Emulator
  *    Description - Implements the  CHIP-8 interpreter,
 *                   and models it the virtual CPU.
- *                  
+ *
  */
-use crate::{Chip8Display, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::breakpoint::{BreakEvent, BreakKind, BreakReason, Breakpoint, Register};
+use crate::display::Chip8Display;
+use crate::error::Chip8Error;
+use crate::instruction::{Instruction, LONG_LDI_OPCODE};
+use crate::keypad::Keypad;
+use crate::quirks::Quirks;
+use crate::rng::Chip8Rng;
+use crate::state::{self, StateError, StateReader, StateWriter, STATE_MAGIC, STATE_VERSION};
+use crate::timers::Timers;
+#[cfg(feature = "debug")]
+use crate::trace::{TraceEntry, Tracer};
 use rand::Rng;
 use std::fs::File;
 use std::io::Read;
+use std::ops::Range;
 
 // Memory location from where the intrepreter
 // starts loading programs
-const PROGRAM_START: u16 = 0x200;
+pub const PROGRAM_START: u16 = 0x200;
 
-// Converts a byte into an array of bits as bools
-// Ex: 0xAA -> [true, false, true, false, true, false, true, false]
-fn byte_to_bools(b: u8) -> [bool; 8] {
-    let mut output = [false; 8];
-    for i in 0..8 {
-        output[i] = (b >> (7 - i)) & 0x01 == 1;
-    }
-    output
-}
+// Size of RAM on the original CHIP-8
+pub const RAM_SIZE: usize = 4096;
+// Size of RAM in XO-CHIP mode, the whole 16-bit address space
+pub const XO_CHIP_RAM_SIZE: usize = 65536;
+
+// Default pitch of the XO-CHIP audio pattern, playing it back at 4000 Hz
+const DEFAULT_PITCH: u8 = 64;
+
+// Memory location of the SUPER-CHIP big font,
+// placed right after the regular font
+const BIG_FONT_START: u16 = 0x50;
 
-// Struct for the 4kB of RAM that the CPU has
+// 8x10 sprites for hex digits 0 to F, used by the SUPER-CHIP
+const BIG_FONT: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+// Struct for the RAM that the CPU has, 4kB
+// normally and 64 kB in XO-CHIP mode
 struct Ram {
-    data: [u8; 4096],
+    data: Vec<u8>,
+    // Addresses accessed by the executing instruction, and whether
+    // they were written. Only kept while memory watchpoints are set
+    log: Option<Vec<(usize, bool)>>,
 }
 
 impl Ram {
     // Initializes the RAM, as all zeros except for 0x00 t0 0x1FF
     // which are initialized to hold sprites for hex digits 0 to F
-    pub fn new() -> Self {
-        let mut data = [0_u8; 4096];
+    pub fn new(size: usize) -> Self {
+        let mut data = vec![0_u8; size];
         // Digit 0
         data[0..5].copy_from_slice(&[0xF0, 0x90, 0x90, 0x90, 0xF0]);
         // Digit 1
@@ -68,8 +105,51 @@ impl Ram {
         data[70..75].copy_from_slice(&[0xF0, 0x80, 0xF0, 0x80, 0xF0]);
         // Digit F
         data[75..80].copy_from_slice(&[0xF0, 0x80, 0xF0, 0x80, 0x80]);
-        Ram { data }
+        // SUPER-CHIP big font
+        let big_font = BIG_FONT_START as usize;
+        data[big_font..big_font + BIG_FONT.len()].copy_from_slice(&BIG_FONT);
+        Ram { data, log: None }
+    }
+
+    // Reads a byte from RAM
+    // Returns an error if the address is outside of RAM
+    fn read(&mut self, addr: usize) -> Result<u8, Chip8Error> {
+        if let Some(log) = &mut self.log {
+            log.push((addr, false));
+        }
+        self.data
+            .get(addr)
+            .copied()
+            .ok_or(Chip8Error::MemoryOutOfBounds { addr })
     }
+
+    // Writes a byte to RAM
+    // Returns an error if the address is outside of RAM
+    fn write(&mut self, addr: usize, val: u8) -> Result<(), Chip8Error> {
+        if let Some(log) = &mut self.log {
+            log.push((addr, true));
+        }
+        match self.data.get_mut(addr) {
+            Some(byte) => {
+                *byte = val;
+                Ok(())
+            }
+            None => Err(Chip8Error::MemoryOutOfBounds { addr }),
+        }
+    }
+}
+
+// The result of a single CPU tick
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepOutcome {
+    /// An instruction was fetched and executed
+    Executed,
+    /// Execution is held, waiting for a key press
+    Waiting,
+    /// The program has exited, and no more instructions are executed
+    Exited,
+    /// A breakpoint or watchpoint was hit
+    Break(BreakReason),
 }
 
 // Implement display trait for nice display of the
@@ -92,13 +172,9 @@ pub struct Cpu {
     v: [u8; 16],
     /// 16x16-bit stack
     stack: [u16; 16],
-    /// Delay timer register
-    /// Decremented by 1 each cycle
-    dt: u8,
-    /// Sound timer register
-    /// Decremented by 1 each cycle
-    st: u8,
-    /// 4 kB (4096 bytes) of RAM
+    /// Delay and sound timer registers
+    timers: Timers,
+    /// 4 kB (4096 bytes) of RAM, or 64 kB in XO-CHIP mode
     ram: Ram,
     /// Display connected to the CPU
     pub display: Chip8Display,
@@ -114,59 +190,142 @@ pub struct Cpu {
     pub cycle: u64,
     /// The clock speed of the device in Hz
     pub clock_speed: usize,
-    /// A vector that contains all currently held keys.
+    /// The state of the 16-key keypad.
     /// Used for CPU instructions that do different things
     /// depending on if a certain key is pressed
-    pub pressed_keys: [bool; 16],
+    pub keypad: Keypad,
     /// Holds CPU execution while true
     /// used for an instruction that holds CPU execution
     /// until a key is pressed
     hold_flag: bool,
+    /// Set at every 60 Hz display refresh, and cleared when
+    /// a sprite is drawn. Used by the display wait quirk
+    vblank: bool,
+    /// Behaviour of the ambiguous opcodes
+    pub quirks: Quirks,
+    /// The SUPER-CHIP RPL user flags, that persist across resets
+    rpl: [u8; 16],
+    /// Set when the program has exited
+    exited: bool,
+    /// Enables the XO-CHIP memory size
+    xo_chip: bool,
+    /// XO-CHIP audio pattern buffer, 128 1-bit samples
+    audio_pattern: [u8; 16],
+    /// XO-CHIP audio pattern playback pitch
+    pitch: u8,
+    /// Hash of the loaded ROM, stored in save states
+    rom_hash: u64,
+    /// Seed of the random number generator, used again on every reset
+    seed: u64,
+    /// Random number generator used by CXKK
+    rng: Chip8Rng,
+    /// Range of RAM read by the last DRW, for sprite viewers
+    last_sprite: Option<(usize, usize)>,
+    /// Breakpoints and watchpoints that stop tick
+    pub breakpoints: Vec<Breakpoint>,
+    /// Records the executed instructions when set
+    #[cfg(feature = "debug")]
+    pub tracer: Option<Tracer>,
+    /// Address of the execution breakpoint that was just hit, which
+    /// is not hit again when execution resumes from it
+    resume_from: Option<u16>,
     /// Variable that holds the loaded instruction in each cycle
     inst: u16,
 }
 
+// Builder for a Cpu with settings other than the defaults
+#[derive(Clone, Copy, Debug)]
+pub struct CpuBuilder {
+    clock_speed: usize,
+    seed: Option<u64>,
+    quirks: Quirks,
+    xo_chip: bool,
+}
+
+impl CpuBuilder {
+    pub fn new(clock_speed: usize) -> Self {
+        CpuBuilder {
+            clock_speed,
+            seed: None,
+            quirks: Quirks::default(),
+            xo_chip: false,
+        }
+    }
+
+    // Seeds the random number generator, making runs reproducible.
+    // Without a seed, a random one is chosen
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    pub fn xo_chip(mut self, enabled: bool) -> Self {
+        self.xo_chip = enabled;
+        self
+    }
+
+    pub fn build(self) -> Cpu {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let mut cpu = Cpu::with_seed(self.clock_speed, seed);
+        cpu.quirks = self.quirks;
+        cpu.set_xo_chip(self.xo_chip);
+        cpu
+    }
+}
+
 impl Cpu {
+    // Creates a CPU with a randomly seeded random number generator
     pub fn new(clock_speed: usize) -> Self {
+        Cpu::with_seed(clock_speed, rand::random())
+    }
+
+    pub fn builder(clock_speed: usize) -> CpuBuilder {
+        CpuBuilder::new(clock_speed)
+    }
+
+    fn with_seed(clock_speed: usize, seed: u64) -> Self {
         Cpu {
             v: [0x00; 16],
             stack: [0_u16; 16],
-            dt: 0x00,
-            st: 0x00,
-            ram: Ram::new(),
+            timers: Timers::new(),
+            ram: Ram::new(RAM_SIZE),
             display: Chip8Display::new(),
             pc: PROGRAM_START,
             sp: 0x00,
             i: 0x0000,
             cycle: 0,
             clock_speed,
-            pressed_keys: [false; 16],
+            keypad: Keypad::new(),
             hold_flag: false,
+            vblank: true,
+            quirks: Quirks::default(),
+            rpl: [0; 16],
+            exited: false,
+            xo_chip: false,
+            audio_pattern: [0; 16],
+            pitch: DEFAULT_PITCH,
+            rom_hash: state::rom_hash(&[]),
+            seed,
+            rng: Chip8Rng::new(seed),
+            last_sprite: None,
+            breakpoints: vec![],
+            #[cfg(feature = "debug")]
+            tracer: None,
+            resume_from: None,
             inst: 0x0000,
         }
     }
 
-    //Functions that parse out operation arguments from opcode
-    fn nnn(&mut self) -> u16 {
-        self.inst & 0x0FFF
-    }
-
-    fn x(&mut self) -> u8 {
+    // Parses out the x register argument from the loaded opcode
+    fn x(&self) -> u8 {
         ((self.inst & 0x0F00) >> 8) as u8
     }
 
-    fn y(&mut self) -> u8 {
-        ((self.inst & 0x00F0) >> 4) as u8
-    }
-
-    fn kk(&mut self) -> u8 {
-        (self.inst & 0x00FF) as u8
-    }
-
-    fn n(&mut self) -> u8 {
-        (self.inst & 0x000F) as u8
-    }
-
     // Dumps the state of the CPU memory and stack to stdout
     pub fn core_dump(&self) {
         println!("ERROR!\n Core dump:\n\tCycles: {}", self.cycle);
@@ -182,54 +341,319 @@ impl Cpu {
     pub fn reset(&mut self) {
         self.v = [0x00; 16];
         self.stack = [0; 16];
-        self.dt = 0x00;
+        self.timers = Timers::new();
         self.sp = 0x00;
         self.pc = PROGRAM_START;
-        self.st = 0x00;
         self.i = 0x00;
         self.cycle = 0;
-        self.pressed_keys = [false; 16];
+        self.keypad.clear();
         self.hold_flag = false;
+        self.vblank = true;
+        self.exited = false;
         self.display = Chip8Display::new();
-        self.ram = Ram::new();
+        self.ram = Ram::new(self.ram_size());
+        self.audio_pattern = [0; 16];
+        self.pitch = DEFAULT_PITCH;
+        self.rng = Chip8Rng::new(self.seed);
+        self.last_sprite = None;
+        self.resume_from = None;
+    }
+
+    // Returns the seed of the random number generator
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Reseeds the random number generator, the new
+    // seed is also used by every following reset
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Chip8Rng::new(seed);
+    }
+
+    // Enables or disables XO-CHIP mode, giving programs 64 kB of RAM
+    // Resets the CPU, so it should be set before loading a ROM
+    pub fn set_xo_chip(&mut self, enabled: bool) {
+        self.xo_chip = enabled;
+        self.reset();
+    }
+
+    pub fn is_xo_chip(&self) -> bool {
+        self.xo_chip
+    }
+
+    // Size of the RAM in the current mode
+    pub fn ram_size(&self) -> usize {
+        if self.xo_chip {
+            XO_CHIP_RAM_SIZE
+        } else {
+            RAM_SIZE
+        }
     }
 
     //Loads a chip 8 ROM into memory and resets the CPU
     pub fn load_rom(&mut self, path: &str) -> std::io::Result<()> {
-        self.reset();
         let mut file = File::open(path)?;
         let mut contents: Vec<u8> = vec![];
         file.read_to_end(&mut contents)?;
-        self.ram.data[0x200..0x200 + contents.len()].clone_from_slice(&contents);
+        self.load_bytes(&contents)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    //Loads a chip 8 program from memory and resets the CPU
+    pub fn load_bytes(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        self.reset();
+        let start = PROGRAM_START as usize;
+        if start + rom.len() > self.ram.data.len() {
+            return Err(Chip8Error::RomTooLarge { size: rom.len() });
+        }
+        self.ram.data[start..start + rom.len()].clone_from_slice(rom);
+        self.rom_hash = state::rom_hash(rom);
+        Ok(())
+    }
+
+    // Returns the hash of the loaded ROM
+    pub fn rom_hash(&self) -> u64 {
+        self.rom_hash
+    }
+
+    // Serializes the state of the running program. The keypad,
+    // quirks and clock speed are settings of the frontend and
+    // are not part of the state
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = StateWriter::default();
+        w.bytes(&STATE_MAGIC);
+        w.u16(STATE_VERSION);
+        w.u64(self.rom_hash);
+        w.bytes(&self.v);
+        for addr in self.stack {
+            w.u16(addr);
+        }
+        w.u8(self.sp);
+        w.u16(self.pc);
+        w.u16(self.i);
+        w.u8(self.timers.dt);
+        w.u8(self.timers.st);
+        w.u64(self.cycle);
+        w.bool(self.hold_flag);
+        w.bool(self.vblank);
+        w.bool(self.exited);
+        w.bool(self.xo_chip);
+        w.bytes(&self.rpl);
+        w.bytes(&self.audio_pattern);
+        w.u8(self.pitch);
+        w.u16(self.inst);
+        w.u64(self.seed);
+        w.u64(self.rng.state());
+        w.buffer(&self.ram.data);
+        w.u16(self.display.width() as u16);
+        w.u16(self.display.height() as u16);
+        w.u8(self.display.planes());
+        w.buffer(self.display.screen());
+        w.data
+    }
+
+    // Restores a state written by save_state. The state is refused if it
+    // was saved from a different ROM, and the CPU is left untouched on errors
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut r = StateReader::new(data);
+        if r.array::<4>()? != STATE_MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        let version = r.u16()?;
+        if version != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion { version });
+        }
+        let hash = r.u64()?;
+        if hash != self.rom_hash {
+            return Err(StateError::RomMismatch {
+                expected: self.rom_hash,
+                found: hash,
+            });
+        }
+        let v = r.array::<16>()?;
+        let mut stack = [0; 16];
+        for addr in stack.iter_mut() {
+            *addr = r.u16()?;
+        }
+        let sp = r.u8()?;
+        let pc = r.u16()?;
+        let i = r.u16()?;
+        let timers = Timers {
+            dt: r.u8()?,
+            st: r.u8()?,
+        };
+        let cycle = r.u64()?;
+        let hold_flag = r.bool()?;
+        let vblank = r.bool()?;
+        let exited = r.bool()?;
+        let xo_chip = r.bool()?;
+        let rpl = r.array::<16>()?;
+        let audio_pattern = r.array::<16>()?;
+        let pitch = r.u8()?;
+        let inst = r.u16()?;
+        let seed = r.u64()?;
+        let rng = Chip8Rng::new(r.u64()?);
+        let ram = r.buffer()?.to_vec();
+        let width = r.u16()? as usize;
+        let height = r.u16()? as usize;
+        let planes = r.u8()?;
+        let screen = r.buffer()?.to_vec();
+        r.finish()?;
+
+        let ram_size = if xo_chip { XO_CHIP_RAM_SIZE } else { RAM_SIZE };
+        if sp as usize > stack.len() || ram.len() != ram_size {
+            return Err(StateError::Corrupt);
+        }
+        let display =
+            Chip8Display::from_parts(width, height, planes, screen).ok_or(StateError::Corrupt)?;
+
+        self.v = v;
+        self.stack = stack;
+        self.sp = sp;
+        self.pc = pc;
+        self.i = i;
+        self.timers = timers;
+        self.cycle = cycle;
+        self.hold_flag = hold_flag;
+        self.vblank = vblank;
+        self.exited = exited;
+        self.xo_chip = xo_chip;
+        self.rpl = rpl;
+        self.audio_pattern = audio_pattern;
+        self.pitch = pitch;
+        self.inst = inst;
+        self.seed = seed;
+        self.rng = rng;
+        self.ram = Ram {
+            data: ram,
+            log: None,
+        };
+        self.last_sprite = None;
+        self.resume_from = None;
+        self.display = display;
         Ok(())
     }
 
+    // Reads the big endian 16-bit word at addr
+    // Instruction fetches are not seen by read watchpoints
+    fn read_word(&self, addr: u16) -> Result<u16, Chip8Error> {
+        let byte = |addr: usize| {
+            self.ram
+                .data
+                .get(addr)
+                .copied()
+                .ok_or(Chip8Error::MemoryOutOfBounds { addr })
+        };
+        Ok(((byte(addr as usize)? as u16) << 8) | byte(addr as usize + 1)? as u16)
+    }
+
+    // Returns the general purpose registers V0 to VF
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.v
+    }
+
+    // Returns the address of the next instruction
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    // Returns the I register
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    // Returns the whole RAM
+    pub fn memory(&self) -> &[u8] {
+        &self.ram.data
+    }
+
+    // Returns the return addresses on the stack, oldest first
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+
+    // Returns the delay and sound timer registers
+    pub fn timers(&self) -> &Timers {
+        &self.timers
+    }
+
+    // Sets a register, for debuggers. Values are truncated to the
+    // width of the register, and SP is limited to the stack depth
+    pub fn set_register(&mut self, register: Register, value: u16) {
+        match register {
+            Register::V(x) => self.v[x as usize & 0xF] = value as u8,
+            Register::I => self.i = value,
+            Register::Pc => self.pc = value,
+            Register::Sp => self.sp = value.min(self.stack.len() as u16) as u8,
+            Register::Dt => self.timers.dt = value as u8,
+            Register::St => self.timers.st = value as u8,
+        }
+        self.resume_from = None;
+    }
+
+    // Writes bytes to RAM starting at addr, for debuggers. Nothing is
+    // written if any of the bytes would fall outside of RAM
+    pub fn write_memory(&mut self, addr: usize, bytes: &[u8]) -> Result<(), Chip8Error> {
+        let end = match addr.checked_add(bytes.len()) {
+            Some(end) if end <= self.ram.data.len() => end,
+            _ => {
+                let addr = addr.saturating_add(bytes.len()).saturating_sub(1);
+                return Err(Chip8Error::MemoryOutOfBounds { addr });
+            }
+        };
+        self.ram.data[addr..end].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    // Returns the range of RAM that the last DRW read its sprite from
+    pub fn last_sprite(&self) -> Option<Range<usize>> {
+        self.last_sprite.map(|(start, end)| start..end)
+    }
+
+    // Returns the XO-CHIP audio pattern buffer, 128 1-bit samples
+    // played back from the most significant bit of the first byte
+    pub fn audio_pattern(&self) -> &[u8; 16] {
+        &self.audio_pattern
+    }
+
+    // Returns the rate in Hz that the XO-CHIP audio pattern
+    // is played back at, set by the pitch register
+    pub fn audio_pattern_rate(&self) -> f64 {
+        4000.0 * 2_f64.powf((self.pitch as f64 - 64.0) / 48.0)
+    }
+
     // If any key is pressed Some with the key value is returned
     // else None is retrurned
     fn get_pressed_key(&self) -> Option<usize> {
-        self.pressed_keys.iter().position(|&x| x == true)
+        self.keypad.first_pressed()
     }
 
     // Returns the value ontop of the stack and
     // decrements the stack pointer
-    // PANICs if the stack is empty i.e stack pointer is 0
-    fn stack_pop(&mut self) -> u16 {
+    // Returns an error if the stack is empty i.e stack pointer is 0
+    fn stack_pop(&mut self) -> Result<u16, Chip8Error> {
         if self.sp == 0 {
-            panic!("Stack underflow!");
+            Err(Chip8Error::StackUnderflow {
+                addr: self.pc.wrapping_sub(2),
+            })
         } else {
             self.sp -= 1;
-            self.stack[self.sp as usize]
+            Ok(self.stack[self.sp as usize])
         }
     }
 
     // Pushes a value onto the stack
-    // PANICs if a push is attempted when the stack is full
-    fn stack_push(&mut self, val: u16) {
+    // Returns an error if a push is attempted when the stack is full
+    fn stack_push(&mut self, val: u16) -> Result<(), Chip8Error> {
         if self.sp == 16 {
-            panic!("Stack overflow!");
+            Err(Chip8Error::StackOverflow {
+                addr: self.pc.wrapping_sub(2),
+            })
         } else {
             self.stack[self.sp as usize] = val;
             self.sp += 1;
+            Ok(())
         }
     }
 
@@ -262,150 +686,64 @@ impl Cpu {
     // while a timer is active it is decremented by 1
     // at a rate of 60 Hz until it deactivates
     pub fn update_timers(&mut self) {
-        if self.dt > 0 {
-            self.dt -= 1;
-        }
-        if self.st > 0 {
-            self.st -= 1;
-        }
+        self.timers.update();
+        self.vblank = true;
     }
 
     // Main function of the CPU
     // Executes a clock cycles, and executing instructions
-    pub fn tick(&mut self) {
+    // Returns an error if the instruction could not be executed,
+    // in which case the CPU is left in the state it faulted in
+    pub fn tick(&mut self) -> Result<StepOutcome, Chip8Error> {
+        if self.exited {
+            return Ok(StepOutcome::Exited);
+        }
+        let mut outcome;
         // Decrement timer registers with wrapping
         if !self.hold_flag {
-            // Get the two insruction bytes
-            let inst_hi = self.ram.data[self.pc as usize];
-            self.pc += 1;
-            let inst_lo = self.ram.data[self.pc as usize];
-            self.pc += 1;
-            self.inst = ((inst_hi as u16) << 8) | inst_lo as u16;
-            // Match and dispatch instruction function
-            match (inst_hi & 0xF0) >> 4 {
-                0x0 => {
-                    if inst_lo == 0xE0 {
-                        self.cls();
-                    } else if inst_lo == 0xEE {
-                        self.ret();
-                    } else {
-                        let nnn = self.nnn();
-                        self.sys(nnn);
-                    }
+            outcome = StepOutcome::Executed;
+            let addr = self.pc;
+            if self.resume_from.take() != Some(addr) {
+                if let Some(reason) = self.exec_break(addr) {
+                    self.resume_from = Some(addr);
+                    return Ok(StepOutcome::Break(reason));
                 }
-                0x1 => {
-                    let nnn = self.nnn();
-                    self.jmp(nnn);
-                }
-                0x2 => {
-                    let nnn = self.nnn();
-                    self.call(nnn);
-                }
-                0x3 => {
-                    let kk = self.kk();
-                    let x = self.x();
-                    self.se(x, kk);
-                }
-                0x4 => {
-                    let kk = self.kk();
-                    let x = self.x();
-                    self.sne(x, kk);
-                }
-                0x5 => {
-                    let x = self.x();
-                    let y = self.y();
-                    self.sexy(x, y);
-                }
-                0x6 => {
-                    let kk = self.kk();
-                    let x = self.x();
-                    self.ld(x, kk);
-                }
-                0x7 => {
-                    let kk = self.kk();
-                    let x = self.x();
-                    self.add(x, kk);
-                }
-                // General purpose register instructions
-                // for arithmetic and logical operations
-                0x8 => {
-                    let x = self.x();
-                    let y = self.y();
-                    match inst_lo & 0x0F {
-                        0x0 => self.ldxy(x, y),
-                        0x1 => self.or(x, y),
-                        0x2 => self.and(x, y),
-                        0x3 => self.xor(x, y),
-                        0x4 => self.adc(x, y),
-                        0x5 => self.sub(x, y),
-                        0x6 => self.shr(x),
-                        0x7 => self.subn(x, y),
-                        0xE => self.shl(x),
-                        _ => self.ill(),
-                    }
-                }
-                0x9 => {
-                    let x = self.x();
-                    let y = self.y();
-                    self.snexy(x, y);
-                }
-                0xA => {
-                    let nnn = self.nnn();
-                    self.ldi(nnn);
-                }
-                0xB => {
-                    let nnn = self.nnn();
-                    self.jpv0(nnn);
-                }
-                0xC => {
-                    let kk = self.kk();
-                    let x = self.x();
-                    self.rnd(x, kk);
-                }
-                0xD => {
-                    let x = self.x();
-                    let y = self.y();
-                    let n = self.n();
-                    self.drw(x, y, n);
-                }
-                0xE => {
-                    let x = self.x();
-                    match inst_lo {
-                        0x9E => self.skp(x),
-                        0xA1 => self.sknp(x),
-                        _ => self.ill(),
-                    }
-                }
-                0xF => {
-                    let x = self.x();
-                    match inst_lo {
-                        0x07 => self.ldvdt(x),
-                        0x0A => match self.get_pressed_key() {
-                            Some(key) => {
-                                self.ldk(x, key as u8);
-                            }
-                            None => self.hold_flag = true,
-                        },
-                        0x15 => self.lddt(x),
-                        0x18 => self.ldst(x),
-                        0x1E => self.addi(x),
-                        0x29 => self.ldsi(x),
-                        0x33 => self.ldbcd(x),
-                        0x55 => self.cpvi(x),
-                        0x65 => self.ldiv(x),
-                        _ => self.ill(),
-                    }
-                }
-                // Illegal instruction
-                _ => self.ill(),
+            }
+            // Get the two insruction bytes
+            self.inst = self.read_word(self.pc)?;
+            self.pc = self.pc.wrapping_add(2);
+            let inst = if self.inst == LONG_LDI_OPCODE {
+                // Four byte instruction, with the address in the next word
+                let next = self.read_word(self.pc)?;
+                self.pc = self.pc.wrapping_add(2);
+                Instruction::decode_long(self.inst, next)
+            } else {
+                Instruction::decode(self.inst)
+            };
+            let inst = inst.map_err(|e| Chip8Error::IllegalOpcode {
+                addr,
+                opcode: e.opcode,
+            })?;
+            if self.watches_memory() || self.tracing() {
+                self.ram.log = Some(vec![]);
+            }
+            let old_v = self.v;
+            let result = self.execute(inst);
+            let accesses = self.ram.log.take().unwrap_or_default();
+            result?;
+            #[cfg(feature = "debug")]
+            self.trace(addr, inst, &old_v, &accesses);
+            if let Some(reason) = self.watch_break(addr, &old_v, &accesses) {
+                outcome = StepOutcome::Break(reason);
             }
         } else {
+            outcome = StepOutcome::Waiting;
             match self.get_pressed_key() {
                 Some(key) => {
                     // Fetch the value x from the last instruction
                     // that was loaded before sleep
                     let x = self.x();
-                    self.ldk(x as u8, key as u8);
+                    self.ldk(x, key as u8);
                     self.hold_flag = false;
                 }
                 None => {
@@ -416,10 +754,112 @@ impl Cpu {
         }
         // Update sound timers if every 1/60 seconds
         let cycles_per_60hz = ((1.0 / 60.0) / (1.0 / self.clock_speed as f64)).round() as u64;
-        if self.cycle % cycles_per_60hz == 0 {
+        if self.cycle.is_multiple_of(cycles_per_60hz.max(1)) {
             self.update_timers();
         }
         self.cycle += 1;
+        Ok(outcome)
+    }
+
+    // Returns the execution breakpoint at addr whose condition holds, if any
+    fn exec_break(&self, addr: u16) -> Option<BreakReason> {
+        self.breakpoints
+            .iter()
+            .enumerate()
+            .find(|(_, bp)| bp.kind == BreakKind::Exec(addr) && self.condition_holds(bp))
+            .map(|(index, _)| BreakReason {
+                index,
+                pc: addr,
+                event: BreakEvent::Exec,
+            })
+    }
+
+    fn watches_memory(&self) -> bool {
+        self.breakpoints
+            .iter()
+            .any(|bp| matches!(bp.kind, BreakKind::Read { .. } | BreakKind::Write { .. }))
+    }
+
+    // Checks the watchpoints against the memory accesses and register
+    // changes of the instruction at pc, that has just been executed
+    fn watch_break(
+        &self,
+        pc: u16,
+        old_v: &[u8; 16],
+        accesses: &[(usize, bool)],
+    ) -> Option<BreakReason> {
+        for (index, bp) in self.breakpoints.iter().enumerate() {
+            let event = match bp.kind {
+                BreakKind::Exec(_) => None,
+                BreakKind::Read { start, end } | BreakKind::Write { start, end } => {
+                    let write = matches!(bp.kind, BreakKind::Write { .. });
+                    accesses
+                        .iter()
+                        .find(|&&(addr, w)| {
+                            w == write && (start as usize..=end as usize).contains(&addr)
+                        })
+                        .map(|&(addr, _)| {
+                            let addr = addr as u16;
+                            if write {
+                                BreakEvent::Write { addr }
+                            } else {
+                                BreakEvent::Read { addr }
+                            }
+                        })
+                }
+                BreakKind::Register(x) => {
+                    let (old, new) = (old_v[x as usize & 0xF], self.v[x as usize & 0xF]);
+                    (old != new).then_some(BreakEvent::Register { x, old, new })
+                }
+            };
+            if let Some(event) = event {
+                if self.condition_holds(bp) {
+                    return Some(BreakReason { index, pc, event });
+                }
+            }
+        }
+        None
+    }
+
+    #[cfg(feature = "debug")]
+    fn tracing(&self) -> bool {
+        self.tracer.is_some()
+    }
+
+    #[cfg(not(feature = "debug"))]
+    fn tracing(&self) -> bool {
+        false
+    }
+
+    // Hands the instruction at pc, that has just been executed, to the tracer
+    #[cfg(feature = "debug")]
+    fn trace(&mut self, pc: u16, inst: Instruction, old_v: &[u8; 16], accesses: &[(usize, bool)]) {
+        let tracer = match &mut self.tracer {
+            Some(tracer) => tracer,
+            None => return,
+        };
+        let registers = (0..16)
+            .filter(|&x| old_v[x] != self.v[x])
+            .map(|x| (x as u8, old_v[x], self.v[x]))
+            .collect();
+        let writes = accesses
+            .iter()
+            .filter(|&&(_, write)| write)
+            .map(|&(addr, _)| (addr as u16, self.ram.data[addr]))
+            .collect();
+        tracer.record(TraceEntry {
+            cycle: self.cycle,
+            pc,
+            opcode: self.inst,
+            inst,
+            registers,
+            i: self.i,
+            writes,
+        });
+    }
+
+    fn condition_holds(&self, bp: &Breakpoint) -> bool {
+        bp.condition.is_none_or(|c| c.holds(self))
     }
 
     // No operation. CPU idles
@@ -427,13 +867,103 @@ impl Cpu {
         self.cycle += 1;
     }
 
-    //Illegal operation
-    fn ill(&mut self) {
-        self.core_dump();
-        panic!(
-            "Illegal instruction {:#06X} provided! Dumping core!",
-            self.inst
-        );
+    // Number of CPU cycles executed in each 60 Hz frame
+    pub fn cycles_per_frame(&self) -> usize {
+        (self.clock_speed as f64 / 60.0).round().max(1.0) as usize
+    }
+
+    // Runs the CPU for one 60 Hz frame. Frontends should only change
+    // the keypad between frames, so that a run can be reproduced
+    // from the seed and the keypad state of every frame
+    pub fn run_frame(&mut self) -> Result<StepOutcome, Chip8Error> {
+        self.run_frame_with(|_| {})
+    }
+
+    // Runs the CPU for one 60 Hz frame like run_frame, calling
+    // after_tick after every cycle, e.g. to render audio
+    pub fn run_frame_with<F: FnMut(&Cpu)>(
+        &mut self,
+        mut after_tick: F,
+    ) -> Result<StepOutcome, Chip8Error> {
+        let mut outcome = StepOutcome::Waiting;
+        for _ in 0..self.cycles_per_frame() {
+            outcome = self.tick()?;
+            after_tick(self);
+            if matches!(outcome, StepOutcome::Exited | StepOutcome::Break(..)) {
+                break;
+            }
+        }
+        Ok(outcome)
+    }
+
+    // Executes a single decoded instruction
+    // The program counter is expected to already point
+    // past the instruction being executed
+    pub fn execute(&mut self, inst: Instruction) -> Result<(), Chip8Error> {
+        match inst {
+            Instruction::Sys(nnn) => self.sys(nnn),
+            Instruction::Cls => self.cls(),
+            Instruction::Ret => self.ret()?,
+            Instruction::Jp(nnn) => self.jmp(nnn),
+            Instruction::Call(nnn) => self.call(nnn)?,
+            Instruction::SeByte { x, kk } => self.se(x, kk),
+            Instruction::SneByte { x, kk } => self.sne(x, kk),
+            Instruction::SeReg { x, y } => self.sexy(x, y),
+            Instruction::LdByte { x, kk } => self.ld(x, kk),
+            Instruction::AddByte { x, kk } => self.add(x, kk),
+            Instruction::LdReg { x, y } => self.ldxy(x, y),
+            Instruction::Or { x, y } => self.or(x, y),
+            Instruction::And { x, y } => self.and(x, y),
+            Instruction::Xor { x, y } => self.xor(x, y),
+            Instruction::AddReg { x, y } => self.adc(x, y),
+            Instruction::Sub { x, y } => self.sub(x, y),
+            Instruction::Shr { x, y } => self.shr(x, y),
+            Instruction::Subn { x, y } => self.subn(x, y),
+            Instruction::Shl { x, y } => self.shl(x, y),
+            Instruction::SneReg { x, y } => self.snexy(x, y),
+            Instruction::LdI(nnn) => self.ldi(nnn),
+            Instruction::JpV0(nnn) => self.jpv0(nnn),
+            Instruction::Rnd { x, kk } => self.rnd(x, kk),
+            Instruction::Drw { x, y, n } => {
+                if self.quirks.display_wait && !self.vblank {
+                    // Retry the instruction until the next display refresh
+                    self.pc = self.pc.wrapping_sub(2);
+                } else {
+                    self.drw(x, y, n)?;
+                }
+            }
+            Instruction::Skp { x } => self.skp(x),
+            Instruction::Sknp { x } => self.sknp(x),
+            Instruction::LdVxDt { x } => self.ldvdt(x),
+            Instruction::LdKey { x } => match self.get_pressed_key() {
+                Some(key) => self.ldk(x, key as u8),
+                None => self.hold_flag = true,
+            },
+            Instruction::LdDtVx { x } => self.lddt(x),
+            Instruction::LdStVx { x } => self.ldst(x),
+            Instruction::AddI { x } => self.addi(x),
+            Instruction::LdFont { x } => self.ldsi(x)?,
+            Instruction::LdBcd { x } => self.ldbcd(x)?,
+            Instruction::StoreRegs { x } => self.cpvi(x)?,
+            Instruction::LoadRegs { x } => self.ldiv(x)?,
+            Instruction::ScrollDown(n) => self.display.scroll_down(n as usize),
+            Instruction::ScrollUp(n) => self.display.scroll_up(n as usize),
+            Instruction::ScrollRight => self.display.scroll_right(4),
+            Instruction::ScrollLeft => self.display.scroll_left(4),
+            Instruction::Exit => self.exited = true,
+            Instruction::Lores => self.display.set_hires(false),
+            Instruction::Hires => self.display.set_hires(true),
+            Instruction::LdHiFont { x } => self.ldhf(x)?,
+            Instruction::StoreFlags { x } => self.ldrv(x),
+            Instruction::LoadFlags { x } => self.ldvr(x),
+            Instruction::LdILong(nnnn) => self.i = nnnn,
+            Instruction::Plane(n) => self.display.set_planes(n),
+            Instruction::SaveRange { x, y } => self.save_range(x, y)?,
+            Instruction::LoadRange { x, y } => self.load_range(x, y)?,
+            Instruction::LdAudio => self.ldaudio()?,
+            Instruction::LdPitch { x } => self.pitch = self.v[x as usize],
+        }
+        Ok(())
     }
 
     // Implement CPU instructions
@@ -448,8 +978,9 @@ impl Cpu {
 
     // Sets the program counter to the address value ontop of
     // the stack, and then decrement the stack pointer
-    fn ret(&mut self) {
-        self.pc = self.stack_pop();
+    fn ret(&mut self) -> Result<(), Chip8Error> {
+        self.pc = self.stack_pop()?;
+        Ok(())
     }
 
     // Jump instruction
@@ -458,29 +989,37 @@ impl Cpu {
         self.pc = addrs;
     }
 
-    fn call(&mut self, addrs: u16) {
-        self.stack_push(self.pc);
+    fn call(&mut self, addrs: u16) -> Result<(), Chip8Error> {
+        self.stack_push(self.pc)?;
         self.pc = addrs;
+        Ok(())
+    }
+
+    // Skips over the next instruction, which
+    // is four bytes long if it is F000 NNNN
+    fn skip(&mut self) {
+        let long = self.read_word(self.pc).ok() == Some(LONG_LDI_OPCODE);
+        self.pc = self.pc.wrapping_add(if long { 4 } else { 2 });
     }
 
     // Skips the next instruction if Vx == kk
     fn se(&mut self, vx: u8, byte: u8) {
         if self.v[vx as usize] == byte {
-            self.pc += 2;
+            self.skip();
         }
     }
 
     // Skips the next instruction if Vx != kk
     fn sne(&mut self, vx: u8, byte: u8) {
         if self.v[vx as usize] != byte {
-            self.pc += 2;
+            self.skip();
         }
     }
 
     // Skips the next instruction if Vx == Vy
     fn sexy(&mut self, vx: u8, vy: u8) {
         if self.v[vx as usize] == self.v[vy as usize] {
-            self.pc += 2;
+            self.skip();
         }
     }
 
@@ -502,16 +1041,35 @@ impl Cpu {
     // Sets Vx = Vx OR Vy
     fn or(&mut self, vx: u8, vy: u8) {
         self.v[vx as usize] |= self.v[vy as usize];
+        self.logic_vf_reset();
     }
 
     // Sets Vx = Vx AND Vy
     fn and(&mut self, vx: u8, vy: u8) {
         self.v[vx as usize] &= self.v[vy as usize];
+        self.logic_vf_reset();
     }
 
     // Sets Vx = Vx XOR Vy
     fn xor(&mut self, vx: u8, vy: u8) {
         self.v[vx as usize] ^= self.v[vy as usize];
+        self.logic_vf_reset();
+    }
+
+    // Logical operations reset Vf on the COSMAC VIP
+    fn logic_vf_reset(&mut self) {
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
+    }
+
+    // Returns the register that is shifted by SHR and SHL
+    fn shift_source(&self, vx: u8, vy: u8) -> u8 {
+        if self.quirks.shift_uses_vy {
+            self.v[vy as usize]
+        } else {
+            self.v[vx as usize]
+        }
     }
 
     // Adds Vy to Vx. If overflow occurs Vf is set to 1
@@ -540,9 +1098,11 @@ impl Cpu {
 
     // The least significant bit of Vx is stored in Vf
     // and Vx is then right-shifted by 1 (divided by 2)
-    fn shr(&mut self, vx: u8) {
-        self.v[0xF] = self.v[vx as usize] & 0x01;
-        self.v[vx as usize] >>= 1;
+    // With the shift quirk Vy is shifted into Vx instead
+    fn shr(&mut self, vx: u8, vy: u8) {
+        let val = self.shift_source(vx, vy);
+        self.v[vx as usize] = val >> 1;
+        self.v[0xF] = val & 0x01;
     }
 
     // Subtracts Vx from Vy. If Vy > Vx, Vf is set to 1
@@ -559,15 +1119,17 @@ impl Cpu {
 
     // The significant bit of Vx is stored in Vf
     // and Vx is then left-shifted by 1 (multiplied by 2)
-    fn shl(&mut self, vx: u8) {
-        self.v[0xF] = (self.v[vx as usize] & 0x80) >> 7;
-        self.v[vx as usize] <<= 1;
+    // With the shift quirk Vy is shifted into Vx instead
+    fn shl(&mut self, vx: u8, vy: u8) {
+        let val = self.shift_source(vx, vy);
+        self.v[vx as usize] = val << 1;
+        self.v[0xF] = (val & 0x80) >> 7;
     }
 
     // Skips the next instruction if Vx != Vy
     fn snexy(&mut self, vx: u8, vy: u8) {
         if self.v[vx as usize] != self.v[vy as usize] {
-            self.pc += 2;
+            self.skip();
         }
     }
 
@@ -577,63 +1139,109 @@ impl Cpu {
     }
 
     // Sets PC to the provided address + V0
+    // With the jump quirk the highest nibble of the address
+    // selects the register instead of V0
     fn jpv0(&mut self, addrs: u16) {
-        self.pc = addrs + self.v[0x0] as u16;
+        let reg = if self.quirks.jump_uses_vx {
+            ((addrs & 0x0F00) >> 8) as usize
+        } else {
+            0x0
+        };
+        self.pc = addrs + self.v[reg] as u16;
     }
 
     // Set Vx to a random byte AND:ed with the provided byte kk
     fn rnd(&mut self, vx: u8, byte: u8) {
-        let mut rng = rand::thread_rng();
-        self.v[vx as usize] = rng.gen::<u8>() & byte;
+        self.v[vx as usize] = self.rng.gen::<u8>() & byte;
     }
 
     // Reads n and n-byte sprite from memory starting from the
     // address stored in register I, and XORing it to the screen
     // starting from coordinates (Vx, Vy).
-    // Sprites that crosses the edge screen will be wrapped to the over side
-    fn drw(&mut self, vx: u8, vy: u8, n: u8) {
+    // Sprites that crosses the edge screen will be wrapped to the over side,
+    // or clipped if the clipping quirk is enabled
+    fn drw(&mut self, vx: u8, vy: u8, n: u8) -> Result<(), Chip8Error> {
+        let width = self.display.width();
+        let height = self.display.height();
+        // Dxy0 draws a 16x16 sprite, with two bytes per row
+        let (rows, cols) = if n == 0 { (16, 16) } else { (n as usize, 8) };
         // Flag used to indicate if any pixels on
         // the screen are overwritten
         let mut flag: bool = false;
-        for i in (0..n as usize).into_iter() {
-            let byte = self.ram.data[self.i as usize + i];
-            // Wrap y-cordinate if sprite goes off screen
-            let y = (self.v[vy as usize] as usize + i) % DISPLAY_HEIGHT;
-            for (j, bit) in byte_to_bools(byte).iter().enumerate() {
-                // Wrap x-coordinate if it goes off screen
-                let x = (self.v[vx as usize] as usize + j) % DISPLAY_WIDTH;
-                // Set the flag to true if XORing true and true
-                if self.display.screen[y][x] && *bit {
-                    flag = true;
+        // The starting coordinates always wrap
+        let x0 = self.v[vx as usize] as usize % width;
+        let y0 = self.v[vy as usize] as usize % height;
+        let clip = self.quirks.clip_sprites;
+        // Each selected plane is drawn with its own sprite,
+        // stored one after the other starting at I
+        let mut addr = self.i as usize;
+        for plane in [0x1, 0x2] {
+            if self.display.planes() & plane == 0 {
+                continue;
+            }
+            for i in 0..rows {
+                // Clipped rows are not read, so they can not fault
+                if clip && y0 + i >= height {
+                    break;
+                }
+                // Sprite row, left aligned in 16 bits
+                let row = if cols == 16 {
+                    let addr = addr + 2 * i;
+                    ((self.ram.read(addr)? as u16) << 8) | self.ram.read(addr + 1)? as u16
+                } else {
+                    (self.ram.read(addr + i)? as u16) << 8
+                };
+                // Wrap y-cordinate if sprite goes off screen
+                let y = (y0 + i) % height;
+                for j in 0..cols {
+                    if clip && x0 + j >= width {
+                        break;
+                    }
+                    // Wrap x-coordinate if it goes off screen
+                    let x = (x0 + j) % width;
+                    let bit = (row >> (15 - j)) & 0x01 == 1;
+                    // Set the flag to true if XORing true and true
+                    if self.display.xor(x, y, plane, bit) {
+                        flag = true;
+                    }
                 }
-                self.display.screen[y][x] ^= bit;
             }
+            addr += rows * cols / 8;
         }
+        self.last_sprite = Some((self.i as usize, addr));
         // If pixel is overwritten, set the Vf register to 1, else 0
         if flag {
             self.v[0xF] = 1;
         } else {
             self.v[0xF] = 0;
         }
+        self.vblank = false;
+        Ok(())
     }
 
     // Skips the next instruction if the specified key is currently held
     fn skp(&mut self, vx: u8) {
-        if self.pressed_keys[self.v[vx as usize] as usize] {
-            self.pc += 2;
+        if self
+            .keypad
+            .is_pressed((self.v[vx as usize] & 0x0F) as usize)
+        {
+            self.skip();
         }
     }
 
     // Skips the next instruction if a certain key is not pressed
     fn sknp(&mut self, vx: u8) {
-        if !self.pressed_keys[self.v[vx as usize] as usize] {
-            self.pc += 2;
+        if !self
+            .keypad
+            .is_pressed((self.v[vx as usize] & 0x0F) as usize)
+        {
+            self.skip();
         }
     }
 
     // Loads the value of the delay timer into register Vx
     fn ldvdt(&mut self, vx: u8) {
-        self.v[vx as usize] = self.dt;
+        self.v[vx as usize] = self.timers.dt;
     }
 
     // Holds execution until a key is pressed,
@@ -645,12 +1253,12 @@ impl Cpu {
 
     // Loads the value of Vx into the the delay timer
     fn lddt(&mut self, vx: u8) {
-        self.dt = self.v[vx as usize];
+        self.timers.dt = self.v[vx as usize];
     }
 
     // Loads the value of Vx into the sound timer register
     fn ldst(&mut self, vx: u8) {
-        self.st = self.v[vx as usize];
+        self.timers.st = self.v[vx as usize];
     }
 
     // Adds the contents of register Vx to the 16-bit I register
@@ -659,36 +1267,116 @@ impl Cpu {
     }
 
     // Loads the RAM location of the digit stored in Vx into
-    // the I register. Panics if the digit value is larger than 15
-    fn ldsi(&mut self, vx: u8) {
-        if self.v[vx as usize] <= 0xF {
-            self.i = 5 * self.v[vx as usize] as u16;
+    // the I register. Returns an error if the digit value is larger than 15
+    fn ldsi(&mut self, vx: u8) -> Result<(), Chip8Error> {
+        let digit = self.v[vx as usize];
+        if digit <= 0xF {
+            self.i = 5 * digit as u16;
+            Ok(())
         } else {
-            panic!("Tried to load sprite of an invalid digit!");
+            Err(Chip8Error::InvalidFontDigit { digit })
         }
     }
 
     // Stores the BCD representation of the value in Vx, in I
     // (hudreds in I, tens in I+1, and ones in I+2)
-    fn ldbcd(&mut self, vx: u8) {
+    fn ldbcd(&mut self, vx: u8) -> Result<(), Chip8Error> {
         let idx = self.i as usize;
-        self.ram.data[idx] = (self.v[vx as usize] as f32 / 100.0).floor() as u8;
-        self.ram.data[idx + 1] = ((self.v[vx as usize] % 100) as f32 / 10.0).floor() as u8;
-        self.ram.data[idx + 2] = self.v[vx as usize] % 10;
+        let val = self.v[vx as usize];
+        self.ram.write(idx, val / 100)?;
+        self.ram.write(idx + 1, (val % 100) / 10)?;
+        self.ram.write(idx + 2, val % 10)
     }
 
     // Copies register V0 through Vx into RAM, starting at
     // the address strored in I
-    fn cpvi(&mut self, vx: u8) {
+    fn cpvi(&mut self, vx: u8) -> Result<(), Chip8Error> {
         for j in 0..vx as usize + 1 {
-            self.ram.data[self.i as usize + j] = self.v[j];
+            self.ram.write(self.i as usize + j, self.v[j])?;
         }
+        self.load_store_increment(vx);
+        Ok(())
     }
 
     // Copies values from RAM into registers V0 through Vx
-    fn ldiv(&mut self, vx: u8) {
+    fn ldiv(&mut self, vx: u8) -> Result<(), Chip8Error> {
         for j in 0..vx as usize + 1 {
-            self.v[j] = self.ram.data[self.i as usize + j];
+            self.v[j] = self.ram.read(self.i as usize + j)?;
+        }
+        self.load_store_increment(vx);
+        Ok(())
+    }
+
+    // Copies registers Vx through Vy into RAM starting at I,
+    // in descending order if x > y. I is left unchanged
+    fn save_range(&mut self, vx: u8, vy: u8) -> Result<(), Chip8Error> {
+        for (j, reg) in Self::register_range(vx, vy).enumerate() {
+            self.ram.write(self.i as usize + j, self.v[reg])?;
+        }
+        Ok(())
+    }
+
+    // Copies values from RAM starting at I into registers Vx through Vy,
+    // in descending order if x > y. I is left unchanged
+    fn load_range(&mut self, vx: u8, vy: u8) -> Result<(), Chip8Error> {
+        for (j, reg) in Self::register_range(vx, vy).enumerate() {
+            self.v[reg] = self.ram.read(self.i as usize + j)?;
+        }
+        Ok(())
+    }
+
+    // Register indices from x to y inclusive, counting down if x > y
+    fn register_range(vx: u8, vy: u8) -> Box<dyn Iterator<Item = usize>> {
+        let (x, y) = (vx as usize, vy as usize);
+        if x <= y {
+            Box::new(x..=y)
+        } else {
+            Box::new((y..=x).rev())
+        }
+    }
+
+    // Loads 16 bytes starting at I into the audio pattern buffer
+    fn ldaudio(&mut self) -> Result<(), Chip8Error> {
+        for j in 0..self.audio_pattern.len() {
+            self.audio_pattern[j] = self.ram.read(self.i as usize + j)?;
+        }
+        Ok(())
+    }
+
+    // Loads the RAM location of the big font digit stored in Vx
+    // into the I register. Returns an error if the digit value is larger than 15
+    fn ldhf(&mut self, vx: u8) -> Result<(), Chip8Error> {
+        let digit = self.v[vx as usize];
+        if digit <= 0xF {
+            self.i = BIG_FONT_START + 10 * digit as u16;
+            Ok(())
+        } else {
+            Err(Chip8Error::InvalidFontDigit { digit })
+        }
+    }
+
+    // Copies registers V0 through Vx into the RPL flags
+    fn ldrv(&mut self, vx: u8) {
+        let n = vx as usize + 1;
+        self.rpl[..n].copy_from_slice(&self.v[..n]);
+    }
+
+    // Copies the RPL flags into registers V0 through Vx
+    fn ldvr(&mut self, vx: u8) {
+        let n = vx as usize + 1;
+        self.v[..n].copy_from_slice(&self.rpl[..n]);
+    }
+
+    // Loads and stores leave I pointing past the last register
+    // on the COSMAC VIP, and at the last register on CHIP-48
+    fn load_store_increment(&mut self, vx: u8) {
+        if self.quirks.load_store_increments_i {
+            let step = if self.quirks.load_store_increments_by_x {
+                vx as u16
+            } else {
+                vx as u16 + 1
+            };
+            self.i = self.i.wrapping_add(step);
         }
     }
 }
@@ -0,0 +1,191 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Debugger window of the frontend. Shows the registers,
+ *                  the stack and the disassembly around PC, and lets the
+ *                  user step through the program while it is paused
+ *                  and set breakpoints.
+ */
+use chip8::instruction::LONG_LDI_OPCODE;
+use chip8::{BreakKind, Breakpoint, Cpu, Instruction};
+use ggez_egui::egui;
+
+// Number of instructions shown before and after PC
+const LINES_BEFORE: u16 = 8;
+const LINES_AFTER: u16 = 16;
+
+// Actions requested from the debugger window, carried out by the frontend
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugAction {
+    StepInstruction,
+    StepFrame,
+    StepOver,
+    RunTo(u16),
+    AddBreakpoint(Breakpoint),
+    RemoveBreakpoint(usize),
+}
+
+#[derive(Default)]
+pub struct Debugger {
+    pub open: bool,
+    /// Address selected in the disassembly, used by run to cursor
+    cursor: Option<u16>,
+    /// Breakpoint being typed in by the user
+    new_breakpoint: String,
+    /// Error of the last breakpoint that failed to parse
+    breakpoint_error: Option<String>,
+}
+
+// Decodes the instruction at addr, returning its opcode and
+// instruction. Returns None for data and addresses outside of RAM
+pub fn decode_at(memory: &[u8], addr: u16) -> Option<(u16, Instruction)> {
+    let word = |addr: usize| -> Option<u16> {
+        Some(((*memory.get(addr)? as u16) << 8) | *memory.get(addr + 1)? as u16)
+    };
+    let opcode = word(addr as usize)?;
+    let inst = if opcode == LONG_LDI_OPCODE {
+        Instruction::decode_long(opcode, word(addr as usize + 2)?)
+    } else {
+        Instruction::decode(opcode)
+    };
+    Some((opcode, inst.ok()?))
+}
+
+impl Debugger {
+    // Draws the debugger window. Stepping is only possible while the
+    // emulation is paused, the requested step is returned to the caller
+    pub fn show(&mut self, ctx: &egui::Context, cpu: &Cpu, paused: bool) -> Option<DebugAction> {
+        let mut action = None;
+        let mut open = self.open;
+        egui::Window::new("Debugger")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.set_enabled(paused);
+                    if ui.button("Step").clicked() {
+                        action = Some(DebugAction::StepInstruction);
+                    }
+                    if ui.button("Step over").clicked() {
+                        action = Some(DebugAction::StepOver);
+                    }
+                    if ui.button("Step frame").clicked() {
+                        action = Some(DebugAction::StepFrame);
+                    }
+                    if let Some(cursor) = self.cursor {
+                        if ui.button(format!("Run to {:#05X}", cursor)).clicked() {
+                            action = Some(DebugAction::RunTo(cursor));
+                        }
+                    }
+                });
+                if !paused {
+                    ui.label("Pause the emulation to step through the program");
+                }
+                ui.separator();
+                self.registers(ui, cpu);
+                ui.separator();
+                if let Some(breakpoint) = self.breakpoints(ui, cpu) {
+                    action = Some(breakpoint);
+                }
+                ui.separator();
+                self.disassembly(ui, cpu);
+            });
+        self.open = open;
+        action
+    }
+
+    fn registers(&self, ui: &mut egui::Ui, cpu: &Cpu) {
+        egui::Grid::new("registers").show(ui, |ui| {
+            for (n, v) in cpu.registers().iter().enumerate() {
+                ui.monospace(format!("V{:X} {:02X}", n, v));
+                if n % 4 == 3 {
+                    ui.end_row();
+                }
+            }
+        });
+        ui.monospace(format!(
+            "PC {:04X}  I {:04X}  SP {:X}  DT {:02X}  ST {:02X}",
+            cpu.pc(),
+            cpu.i(),
+            cpu.stack().len(),
+            cpu.timers().delay(),
+            cpu.timers().sound()
+        ));
+        let stack: Vec<String> = cpu.stack().iter().map(|a| format!("{:04X}", a)).collect();
+        ui.monospace(format!("Stack [{}]", stack.join(" ")));
+    }
+
+    // Lists the breakpoints of the CPU, with a field to add new ones in
+    // the same form as they are listed, e.g. `change VF if VF == 1`
+    fn breakpoints(&mut self, ui: &mut egui::Ui, cpu: &Cpu) -> Option<DebugAction> {
+        let mut action = None;
+        for (n, breakpoint) in cpu.breakpoints.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("x").clicked() {
+                    action = Some(DebugAction::RemoveBreakpoint(n));
+                }
+                ui.monospace(format!("{} {}", n, breakpoint));
+            });
+        }
+        ui.horizontal(|ui| {
+            let field = ui.text_edit_singleline(&mut self.new_breakpoint);
+            let entered = field.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+            if ui.button("Add").clicked() || entered {
+                match self.new_breakpoint.parse() {
+                    Ok(breakpoint) => {
+                        action = Some(DebugAction::AddBreakpoint(breakpoint));
+                        self.new_breakpoint.clear();
+                        self.breakpoint_error = None;
+                    }
+                    Err(e) => self.breakpoint_error = Some(e),
+                }
+            }
+        });
+        if let Some(error) = &self.breakpoint_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        action
+    }
+
+    // Disassembles linearly from a bit before PC. Clicking a line
+    // selects it as the target of run to cursor
+    fn disassembly(&mut self, ui: &mut egui::Ui, cpu: &Cpu) {
+        let pc = cpu.pc();
+        let mut addr = pc.saturating_sub(2 * LINES_BEFORE);
+        for _ in 0..LINES_BEFORE + LINES_AFTER {
+            let (text, size) = match decode_at(cpu.memory(), addr) {
+                Some((opcode, inst)) => (format!("{:04X}  {}", opcode, inst), inst.size()),
+                // Words that are not instructions are shown as data,
+                // keeping the listing aligned with PC
+                None => match cpu.memory().get(addr as usize..addr as usize + 2) {
+                    Some(&[hi, lo]) => (
+                        format!(
+                            "{:02X}{:02X}  DW {:#06X}",
+                            hi,
+                            lo,
+                            (hi as u16) << 8 | lo as u16
+                        ),
+                        2,
+                    ),
+                    _ => break,
+                },
+            };
+            let marker = if addr == pc { ">" } else { " " };
+            let breakpoint = cpu
+                .breakpoints
+                .iter()
+                .any(|bp| bp.kind == BreakKind::Exec(addr));
+            let breakpoint = if breakpoint { "*" } else { " " };
+            let line = format!("{}{} {:#05X}  {}", marker, breakpoint, addr, text);
+            let selected = self.cursor == Some(addr);
+            if ui
+                .selectable_label(selected, egui::RichText::new(line).monospace())
+                .clicked()
+            {
+                self.cursor = if selected { None } else { Some(addr) };
+            }
+            addr = addr.wrapping_add(size);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Disassembler for CHIP-8 ROMs. Follows jumps and
+ *                  calls from the program start to separate code
+ *                  from data, such as sprites.
+ */
+use crate::cpu::PROGRAM_START;
+use crate::instruction::{Instruction, LONG_LDI_OPCODE};
+use std::fmt;
+
+// A single line of disassembly
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Line {
+    /// An instruction that is reachable from the program start
+    Code {
+        addr: u16,
+        opcode: u16,
+        inst: Instruction,
+    },
+    /// A byte that is never executed, e.g. sprite data
+    Data { addr: u16, byte: u8 },
+}
+
+impl Line {
+    // Returns the RAM address of the line
+    pub fn addr(&self) -> u16 {
+        match *self {
+            Line::Code { addr, .. } => addr,
+            Line::Data { addr, .. } => addr,
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Line::Code { addr, opcode, inst } => {
+                write!(f, "{:#05X}  {:04X}  {}", addr, opcode, inst)
+            }
+            Line::Data { addr, byte } => {
+                // Data bytes are shown as sprite rows, since
+                // that is what most data in a CHIP-8 ROM is
+                let row: String = (0..8)
+                    .map(|i| {
+                        if (byte >> (7 - i)) & 0x01 == 1 {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                write!(
+                    f,
+                    "{:#05X}  {:02X}    DB {:#04X}  ; {}",
+                    addr, byte, byte, row
+                )
+            }
+        }
+    }
+}
+
+// Returns the addresses execution may continue at after
+// executing the instruction at addr. Skips jump over the
+// whole next instruction, which is four bytes if it is long
+fn successors(addr: u16, inst: Instruction, next_is_long: bool) -> Vec<u16> {
+    let next = addr.wrapping_add(inst.size());
+    let skip = if next_is_long { 4 } else { 2 };
+    match inst {
+        // 0nnn runs machine code on the original hardware, which
+        // the Cpu emulates as a jump, see Cpu::sys
+        Instruction::Jp(nnn) | Instruction::Sys(nnn) => vec![nnn],
+        Instruction::Call(nnn) => vec![nnn, next],
+        // Skips may continue at either of the two following instructions
+        Instruction::SeByte { .. }
+        | Instruction::SneByte { .. }
+        | Instruction::SeReg { .. }
+        | Instruction::SneReg { .. }
+        | Instruction::Skp { .. }
+        | Instruction::Sknp { .. } => vec![next, next.wrapping_add(skip)],
+        // The targets of these can not be known without running the
+        // program. Jump tables of JP V0 are found by disassemble
+        Instruction::Ret | Instruction::JpV0(..) | Instruction::Exit => vec![],
+        _ => vec![next],
+    }
+}
+
+// Disassembles a ROM loaded at PROGRAM_START. Instructions are found
+// by following every jump, call and skip from the program start,
+// anything that is never reached is treated as data
+pub fn disassemble(rom: &[u8]) -> Vec<Line> {
+    let start = PROGRAM_START as usize;
+    let end = start + rom.len();
+    let fetch = |addr: usize| -> Option<u16> {
+        if addr >= start && addr + 1 < end {
+            let i = addr - start;
+            Some(((rom[i] as u16) << 8) | rom[i + 1] as u16)
+        } else {
+            None
+        }
+    };
+
+    // Decodes the instruction at addr, including the
+    // second word of four byte instructions
+    let decode_at = |addr: usize| -> Option<Instruction> {
+        let opcode = fetch(addr)?;
+        if opcode == LONG_LDI_OPCODE {
+            Instruction::decode_long(opcode, fetch(addr + 2)?).ok()
+        } else {
+            Instruction::decode(opcode).ok()
+        }
+    };
+
+    // Flow analysis, marking the start of every reachable instruction
+    let mut is_code = vec![false; rom.len()];
+    let mut queue = vec![PROGRAM_START];
+    while let Some(addr) = queue.pop() {
+        if fetch(addr as usize).is_none() {
+            continue;
+        }
+        let idx = addr as usize - start;
+        if is_code[idx] {
+            continue;
+        }
+        let inst = match decode_at(addr as usize) {
+            Some(inst) => inst,
+            None => continue,
+        };
+        is_code[idx] = true;
+        let next_is_long = fetch(addr as usize + inst.size() as usize) == Some(LONG_LDI_OPCODE);
+        queue.extend(successors(addr, inst, next_is_long));
+        // JP V0, nnn usually indexes a table of jumps at nnn,
+        // so every JP in a row from nnn is taken as reachable
+        if let Instruction::JpV0(base) = inst {
+            let mut entry = base as usize;
+            while let Some(Instruction::Jp(..)) = decode_at(entry) {
+                queue.push(entry as u16);
+                entry += 2;
+            }
+        }
+    }
+
+    // Linear sweep, emitting instructions where the
+    // flow analysis found code and data everywhere else
+    let mut lines = vec![];
+    let mut idx = 0;
+    while idx < rom.len() {
+        let addr = (start + idx) as u16;
+        match (fetch(start + idx), decode_at(start + idx)) {
+            (Some(opcode), Some(inst)) if is_code[idx] => {
+                lines.push(Line::Code { addr, opcode, inst });
+                idx += inst.size() as usize;
+            }
+            _ => {
+                lines.push(Line::Data {
+                    addr,
+                    byte: rom[idx],
+                });
+                idx += 1;
+            }
+        }
+    }
+    lines
+}
@@ -0,0 +1,205 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Implements the display buffer of the CHIP-8
+ *                  interpreter. Rendering is left to the frontend.
+ */
+
+use std::fmt;
+
+// Resolution of the original CHIP-8 display,
+// used by the SUPER-CHIP low resolution mode
+pub const DISPLAY_WIDTH: usize = 64;
+pub const DISPLAY_HEIGHT: usize = 32;
+// Resolution of the SUPER-CHIP high resolution mode
+pub const HIRES_DISPLAY_WIDTH: usize = 128;
+pub const HIRES_DISPLAY_HEIGHT: usize = 64;
+
+// Emulates the Chip8's attached display, which is 64x32
+// in low resolution mode and 128x64 in high resolution mode.
+// XO-CHIP programs draw to two bitplanes, giving each pixel
+// one of four colours
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chip8Display {
+    width: usize,
+    height: usize,
+    // Pixels stored row by row, as a bitmask of
+    // the planes the pixel is lit in
+    screen: Vec<u8>,
+    // Bitmask of the planes that are drawn to, cleared and scrolled
+    planes: u8,
+}
+
+// Default implementation for display
+impl Default for Chip8Display {
+    fn default() -> Self {
+        Chip8Display::new()
+    }
+}
+
+impl Chip8Display {
+    // Creates a new, blank display in low resolution mode
+    // with only the first plane selected
+    pub fn new() -> Self {
+        Chip8Display {
+            width: DISPLAY_WIDTH,
+            height: DISPLAY_HEIGHT,
+            screen: vec![0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            planes: 0x1,
+        }
+    }
+
+    // Clears the selected planes of the screen
+    pub fn clear(&mut self) {
+        let mask = !self.planes;
+        self.screen.iter_mut().for_each(|p| *p &= mask);
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_hires(&self) -> bool {
+        self.width == HIRES_DISPLAY_WIDTH
+    }
+
+    // Switches between the low and high resolution modes,
+    // clearing the screen
+    pub fn set_hires(&mut self, hires: bool) {
+        if hires {
+            self.width = HIRES_DISPLAY_WIDTH;
+            self.height = HIRES_DISPLAY_HEIGHT;
+        } else {
+            self.width = DISPLAY_WIDTH;
+            self.height = DISPLAY_HEIGHT;
+        }
+        self.screen = vec![0; self.width * self.height];
+    }
+
+    // Rebuilds a display from a saved screen buffer. Returns None
+    // if the resolution or the pixel values are not valid
+    pub(crate) fn from_parts(
+        width: usize,
+        height: usize,
+        planes: u8,
+        screen: Vec<u8>,
+    ) -> Option<Self> {
+        let resolution_ok = (width, height) == (DISPLAY_WIDTH, DISPLAY_HEIGHT)
+            || (width, height) == (HIRES_DISPLAY_WIDTH, HIRES_DISPLAY_HEIGHT);
+        if !resolution_ok || screen.len() != width * height || planes > 0x3 {
+            return None;
+        }
+        if screen.iter().any(|&p| p > 0x3) {
+            return None;
+        }
+        Some(Chip8Display {
+            width,
+            height,
+            screen,
+            planes,
+        })
+    }
+
+    // Returns the bitmask of the selected planes
+    pub fn planes(&self) -> u8 {
+        self.planes
+    }
+
+    // Selects the planes that are drawn to, cleared and scrolled
+    pub(crate) fn set_planes(&mut self, planes: u8) {
+        self.planes = planes & 0x3;
+    }
+
+    // Returns true if the pixel at (x, y) is lit in any plane
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.color(x, y) != 0
+    }
+
+    // Returns the colour index of the pixel at (x, y), from 0 to 3.
+    // Bit 0 is set if the pixel is lit in the first plane,
+    // and bit 1 if it is lit in the second plane
+    pub fn color(&self, x: usize, y: usize) -> u8 {
+        self.screen[y * self.width + x]
+    }
+
+    // Returns the whole screen buffer, row by row,
+    // as the colour index of each pixel
+    pub fn screen(&self) -> &[u8] {
+        &self.screen
+    }
+
+    // XORs a pixel onto the given plane of the screen
+    // Returns true if a lit pixel was turned off
+    pub(crate) fn xor(&mut self, x: usize, y: usize, plane: u8, bit: bool) -> bool {
+        let pixel = &mut self.screen[y * self.width + x];
+        let collision = bit && *pixel & plane != 0;
+        if bit {
+            *pixel ^= plane;
+        }
+        collision
+    }
+
+    // Moves the selected planes by (dx, dy) pixels, pixels
+    // moved in from outside of the screen are left unlit
+    fn shift(&mut self, dx: isize, dy: isize) {
+        let old = self.screen.clone();
+        let mask = self.planes;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let sx = x as isize - dx;
+                let sy = y as isize - dy;
+                let in_bounds =
+                    sx >= 0 && sy >= 0 && (sx as usize) < self.width && (sy as usize) < self.height;
+                let moved = if in_bounds {
+                    old[sy as usize * self.width + sx as usize]
+                } else {
+                    0
+                };
+                let idx = y * self.width + x;
+                self.screen[idx] = (old[idx] & !mask) | (moved & mask);
+            }
+        }
+    }
+
+    // Scrolls the screen down by n pixels
+    pub(crate) fn scroll_down(&mut self, n: usize) {
+        self.shift(0, n as isize);
+    }
+
+    // Scrolls the screen up by n pixels
+    pub(crate) fn scroll_up(&mut self, n: usize) {
+        self.shift(0, -(n as isize));
+    }
+
+    // Scrolls the screen right by n pixels
+    pub(crate) fn scroll_right(&mut self, n: usize) {
+        self.shift(n as isize, 0);
+    }
+
+    // Scrolls the screen left by n pixels
+    pub(crate) fn scroll_left(&mut self, n: usize) {
+        self.shift(-(n as isize), 0);
+    }
+}
+
+// Draws the screen as text, one line per row. Unlit pixels are
+// shown as '.', pixels lit in the first plane as '#', in the
+// second plane as '+' and in both planes as '@'
+impl fmt::Display for Chip8Display {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.screen.chunks(self.width) {
+            let line: String = row
+                .iter()
+                .map(|&p| ['.', '#', '+', '@'][p as usize])
+                .collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
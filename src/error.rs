@@ -0,0 +1,48 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Errors that can be raised by the CHIP-8 interpreter
+ *                  while loading or executing a program.
+ */
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Error {
+    /// An opcode that does not map to any instruction was fetched
+    IllegalOpcode { addr: u16, opcode: u16 },
+    /// A CALL was executed with all 16 stack levels in use
+    StackOverflow { addr: u16 },
+    /// A RET was executed with an empty stack
+    StackUnderflow { addr: u16 },
+    /// An instruction tried to read or write outside of RAM
+    MemoryOutOfBounds { addr: usize },
+    /// LD F, Vx was executed with a value in Vx larger than 0xF
+    InvalidFontDigit { digit: u8 },
+    /// The program does not fit in RAM after the program start address
+    RomTooLarge { size: usize },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::IllegalOpcode { addr, opcode } => {
+                write!(f, "Illegal instruction {:#06X} at {:#06X}", opcode, addr)
+            }
+            Chip8Error::StackOverflow { addr } => write!(f, "Stack overflow at {:#06X}", addr),
+            Chip8Error::StackUnderflow { addr } => write!(f, "Stack underflow at {:#06X}", addr),
+            Chip8Error::MemoryOutOfBounds { addr } => {
+                write!(f, "Memory access out of bounds at {:#06X}", addr)
+            }
+            Chip8Error::InvalidFontDigit { digit } => {
+                write!(f, "Tried to load sprite of an invalid digit {:#04X}", digit)
+            }
+            Chip8Error::RomTooLarge { size } => {
+                write!(f, "ROM of {} bytes does not fit in RAM", size)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
@@ -0,0 +1,424 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Stub for the GDB remote serial protocol, letting an
+ *                  external debugger read and write the registers and
+ *                  RAM of the CPU, single-step it and set breakpoints
+ *                  and watchpoints over a TCP connection.
+ */
+use crate::breakpoint::{BreakEvent, BreakKind, Breakpoint, Register};
+use crate::cpu::{Cpu, StepOutcome};
+use crate::error::Chip8Error;
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+// Signals reported to the debugger when the CPU stops
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+
+// Largest packet the stub accepts, reported to the debugger
+const PACKET_SIZE: usize = 0x4000;
+
+// Registers in the order of the g packet, with their size in bytes.
+// Registers are sent big-endian, the byte order of CHIP-8 memory
+fn registers() -> impl Iterator<Item = (Register, usize)> {
+    (0..16).map(|x| (Register::V(x), 1)).chain([
+        (Register::I, 2),
+        (Register::Pc, 2),
+        (Register::Sp, 1),
+        (Register::Dt, 1),
+        (Register::St, 1),
+    ])
+}
+
+// Describes the registers to the debugger, in the order of the g packet
+fn target_xml() -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\"?><!DOCTYPE target SYSTEM \"gdb-target.dtd\">\
+         <target><feature name=\"org.chip8.core\">",
+    );
+    for (register, size) in registers() {
+        let kind = match register {
+            Register::I => "data_ptr",
+            Register::Pc => "code_ptr",
+            _ => "int",
+        };
+        xml.push_str(&format!(
+            "<reg name=\"{}\" bitsize=\"{}\" type=\"{}\"/>",
+            register.to_string().to_lowercase(),
+            size * 8,
+            kind
+        ));
+    }
+    xml.push_str("</feature></target>");
+    xml
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|n| u8::from_str_radix(s.get(n..n + 2)?, 16).ok())
+        .collect()
+}
+
+fn parse_number(s: &str) -> Option<usize> {
+    usize::from_str_radix(s, 16).ok()
+}
+
+// Parses the `addr,length` argument of memory and breakpoint packets
+fn parse_range(s: &str) -> Option<(usize, usize)> {
+    let (addr, len) = s.split_once(',')?;
+    Some((parse_number(addr)?, parse_number(len)?))
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &b| sum.wrapping_add(b))
+}
+
+// What to do after a packet has been handled
+enum Reply {
+    Packet(String),
+    // The debugger detached, the reply is sent before closing
+    Detach(String),
+    // The debugger killed the program, no reply is expected
+    Kill,
+}
+
+pub struct GdbStub {
+    stream: TcpStream,
+    /// Set when the debugger has turned off acknowledgements
+    no_ack: bool,
+    /// Stop reply of the last time the CPU stopped
+    last_stop: String,
+    /// Bytes other than interrupts received while the CPU was running,
+    /// read before the stream by the next packet read
+    pending: VecDeque<u8>,
+}
+
+impl GdbStub {
+    pub fn new(stream: TcpStream) -> Self {
+        GdbStub {
+            stream,
+            no_ack: false,
+            last_stop: format!("S{:02x}", SIGTRAP),
+            pending: VecDeque::new(),
+        }
+    }
+
+    // Waits for a debugger to connect
+    pub fn accept(listener: &TcpListener) -> io::Result<Self> {
+        let (stream, _) = listener.accept()?;
+        stream.set_nodelay(true)?;
+        Ok(GdbStub::new(stream))
+    }
+
+    // Serves the debugger until it detaches, kills the program or
+    // disconnects. The CPU only runs when the debugger resumes it
+    pub fn serve(&mut self, cpu: &mut Cpu) -> io::Result<()> {
+        while let Some(packet) = self.read_packet()? {
+            if packet == "QStartNoAckMode" {
+                self.send("OK")?;
+                self.no_ack = true;
+                continue;
+            }
+            match self.handle(cpu, &packet)? {
+                Reply::Packet(reply) => self.send(&reply)?,
+                Reply::Detach(reply) => return self.send(&reply),
+                Reply::Kill => return Ok(()),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, cpu: &mut Cpu, packet: &str) -> io::Result<Reply> {
+        let error = || "E01".to_string();
+        let (command, args) = packet.split_at(packet.len().min(1));
+        let reply = match command {
+            "?" => self.last_stop.clone(),
+            "g" => registers()
+                .map(|(register, size)| {
+                    let value = register.value(cpu).to_be_bytes();
+                    hex(&value[2 - size..])
+                })
+                .collect(),
+            "G" => match parse_hex(args) {
+                Some(bytes) if bytes.len() == registers().map(|(_, size)| size).sum() => {
+                    let mut bytes = bytes.iter();
+                    for (register, size) in registers() {
+                        let value = bytes
+                            .by_ref()
+                            .take(size)
+                            .fold(0, |value, &b| (value << 8) | b as u16);
+                        cpu.set_register(register, value);
+                    }
+                    "OK".to_string()
+                }
+                _ => error(),
+            },
+            "p" => match parse_number(args).and_then(|n| registers().nth(n)) {
+                Some((register, size)) => hex(&register.value(cpu).to_be_bytes()[2 - size..]),
+                None => error(),
+            },
+            "P" => {
+                let register = args.split_once('=').and_then(|(n, value)| {
+                    let (register, size) = registers().nth(parse_number(n)?)?;
+                    let value = parse_hex(value).filter(|v| v.len() == size)?;
+                    Some((register, value.iter().fold(0, |v, &b| (v << 8) | b as u16)))
+                });
+                match register {
+                    Some((register, value)) => {
+                        cpu.set_register(register, value);
+                        "OK".to_string()
+                    }
+                    None => error(),
+                }
+            }
+            // Reads are cut short at the end of RAM
+            "m" => match parse_range(args) {
+                Some((addr, len)) if addr < cpu.memory().len() => match addr.checked_add(len) {
+                    Some(end) => hex(&cpu.memory()[addr..end.min(cpu.memory().len())]),
+                    None => error(),
+                },
+                _ => error(),
+            },
+            "M" => {
+                let write = args.split_once(':').and_then(|(range, data)| {
+                    let (addr, len) = parse_range(range)?;
+                    Some((addr, parse_hex(data).filter(|data| data.len() == len)?))
+                });
+                match write.map(|(addr, data)| cpu.write_memory(addr, &data)) {
+                    Some(Ok(())) => "OK".to_string(),
+                    _ => error(),
+                }
+            }
+            "s" | "c" => {
+                if let Some(addr) = parse_number(args) {
+                    cpu.set_register(Register::Pc, addr as u16);
+                }
+                self.last_stop = self.resume(cpu, command == "s")?;
+                self.last_stop.clone()
+            }
+            // Hardware breakpoints and access watchpoints are not supported
+            "Z" | "z" if !matches!(args.split(',').next(), Some("0" | "2" | "3")) => String::new(),
+            "Z" | "z" => match self.breakpoint(args) {
+                Some(breakpoint) if command == "Z" => {
+                    cpu.breakpoints.push(breakpoint);
+                    "OK".to_string()
+                }
+                Some(breakpoint) => {
+                    if let Some(n) = cpu.breakpoints.iter().position(|&bp| bp == breakpoint) {
+                        cpu.breakpoints.remove(n);
+                    }
+                    "OK".to_string()
+                }
+                None => error(),
+            },
+            "H" => "OK".to_string(),
+            "D" => return Ok(Reply::Detach("OK".to_string())),
+            "k" => return Ok(Reply::Kill),
+            _ => self.query(packet).unwrap_or_default(),
+        };
+        Ok(Reply::Packet(reply))
+    }
+
+    // Answers the general queries the debugger sends while connecting.
+    // The CPU is reported as the single thread of a running process
+    fn query(&self, packet: &str) -> Option<String> {
+        if packet.starts_with("qSupported") {
+            return Some(format!(
+                "PacketSize={:x};qXfer:features:read+;QStartNoAckMode+",
+                PACKET_SIZE
+            ));
+        }
+        if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            let (offset, len) = parse_range(range)?;
+            let Some(end) = offset.checked_add(len) else {
+                return Some("E01".to_string());
+            };
+            let xml = target_xml();
+            let chunk = xml.get(offset.min(xml.len())..end.min(xml.len()))?;
+            let more = if end < xml.len() { 'm' } else { 'l' };
+            return Some(format!("{}{}", more, chunk));
+        }
+        let reply = match packet {
+            "qAttached" => "1",
+            "qC" => "QC1",
+            "qfThreadInfo" => "m1",
+            "qsThreadInfo" => "l",
+            _ => return None,
+        };
+        Some(reply.to_string())
+    }
+
+    // Parses the `type,addr,kind` argument of Z and z packets. Returns
+    // None if the range does not fit in the 16 bit address space
+    fn breakpoint(&self, args: &str) -> Option<Breakpoint> {
+        let (kind, range) = args.split_once(',')?;
+        let (addr, len) = parse_range(range)?;
+        let start = u16::try_from(addr).ok()?;
+        let end = u16::try_from(addr.checked_add(len.max(1) - 1)?).ok()?;
+        let kind = match kind {
+            "0" => BreakKind::Exec(start),
+            "2" => BreakKind::Write { start, end },
+            "3" => BreakKind::Read { start, end },
+            _ => return None,
+        };
+        Some(Breakpoint::new(kind))
+    }
+
+    // Runs one instruction, or until a breakpoint is hit, the program
+    // exits or faults, or the debugger interrupts. An execution
+    // breakpoint at the address the CPU resumes from is passed, so that
+    // continuing from a breakpoint does not stop at it again at once.
+    // Returns the stop reply
+    fn resume(&mut self, cpu: &mut Cpu, step: bool) -> io::Result<String> {
+        let start = cpu.pc();
+        let mut ticks: usize = 0;
+        loop {
+            let outcome = match cpu.tick() {
+                Ok(outcome) => outcome,
+                Err(Chip8Error::IllegalOpcode { .. }) => return Ok(format!("S{:02x}", SIGILL)),
+                Err(_) => return Ok(format!("S{:02x}", SIGSEGV)),
+            };
+            match outcome {
+                StepOutcome::Exited => return Ok("W00".to_string()),
+                StepOutcome::Break(reason) => match reason.event {
+                    BreakEvent::Exec if ticks == 0 && reason.pc == start => continue,
+                    BreakEvent::Write { addr } => {
+                        return Ok(format!("T{:02x}watch:{:x};", SIGTRAP, addr))
+                    }
+                    BreakEvent::Read { addr } => {
+                        return Ok(format!("T{:02x}rwatch:{:x};", SIGTRAP, addr))
+                    }
+                    _ => return Ok(format!("S{:02x}", SIGTRAP)),
+                },
+                StepOutcome::Executed | StepOutcome::Waiting if step => {
+                    return Ok(format!("S{:02x}", SIGTRAP))
+                }
+                StepOutcome::Executed | StepOutcome::Waiting => {}
+            }
+            ticks += 1;
+            if ticks.is_multiple_of(cpu.cycles_per_frame()) && self.interrupted()? {
+                return Ok(format!("S{:02x}", SIGINT));
+            }
+        }
+    }
+
+    // Checks, without blocking, if the debugger has sent an interrupt.
+    // A disconnect also stops the CPU. Any other bytes are kept, so
+    // that a packet sent while running is not lost
+    fn interrupted(&mut self) -> io::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+        let mut bytes = [0; 64];
+        let result = self.stream.read(&mut bytes);
+        self.stream.set_nonblocking(false)?;
+        match result {
+            Ok(0) => Ok(true),
+            Ok(n) => {
+                let bytes = &bytes[..n];
+                self.pending.extend(bytes.iter().filter(|&&b| b != 0x03));
+                Ok(bytes.contains(&0x03))
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        match self.stream.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    // Reads the next byte of a packet, from the bytes kept while
+    // running first
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        if let Some(byte) = self.pending.pop_front() {
+            return Ok(Some(byte));
+        }
+        self.read_byte()
+    }
+
+    // Reads the next packet, skipping acknowledgements and interrupts
+    // sent while the CPU is stopped. Packets with a bad checksum are
+    // asked for again. Returns None when the debugger disconnects
+    fn read_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            loop {
+                match self.next_byte()? {
+                    Some(b'$') => break,
+                    Some(_) => {}
+                    None => return Ok(None),
+                }
+            }
+            let mut raw = vec![];
+            loop {
+                match self.next_byte()? {
+                    Some(b'#') => break,
+                    Some(b) => raw.push(b),
+                    None => return Ok(None),
+                }
+            }
+            let mut sum = [0; 2];
+            for digit in &mut sum {
+                *digit = match self.next_byte()? {
+                    Some(b) => b,
+                    None => return Ok(None),
+                };
+            }
+            let valid = std::str::from_utf8(&sum)
+                .ok()
+                .and_then(|sum| u8::from_str_radix(sum, 16).ok())
+                == Some(checksum(&raw));
+            if !self.no_ack {
+                self.stream.write_all(if valid { b"+" } else { b"-" })?;
+            }
+            if !valid && !self.no_ack {
+                continue;
+            }
+            // Bytes escaped with '}' are xored with 0x20
+            let mut data = vec![];
+            let mut bytes = raw.into_iter();
+            while let Some(b) = bytes.next() {
+                match b {
+                    b'}' => data.push(bytes.next().unwrap_or(0) ^ 0x20),
+                    b => data.push(b),
+                }
+            }
+            return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+        }
+    }
+
+    // Sends a packet, resending it until the debugger acknowledges it
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        let packet = format!("${}#{:02x}", data, checksum(data.as_bytes()));
+        loop {
+            self.stream.write_all(packet.as_bytes())?;
+            if self.no_ack {
+                return Ok(());
+            }
+            loop {
+                match self.read_byte()? {
+                    Some(b'+') => return Ok(()),
+                    Some(b'-') => break,
+                    Some(_) => {}
+                    None => return Err(ErrorKind::UnexpectedEof.into()),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,889 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - The ggez/egui frontend. Renders the display
+ *                  of the emulator core and feeds it keyboard input.
+ */
+use crate::debugger::{self, DebugAction, Debugger};
+use crate::memory_viewer::MemoryViewer;
+use crate::renderer::{Filter, Renderer, MAX_BLEND_FRAMES};
+use crate::speaker::Speaker;
+use crate::sprite_viewer::SpriteViewer;
+use crate::{RunOptions, DEFAULT_ROM};
+use chip8::audio::DEFAULT_SAMPLE_RATE;
+use chip8::palette::{format_color, parse_color};
+use chip8::Config;
+use chip8::{
+    AudioOutput, BreakReason, Buzzer, BuzzerSettings, Chip8Error, Cpu, Instruction, Keypad, Movie,
+    NullOutput, PalettePreset, QuirksPreset, Rewind, StepOutcome, Tee, WavOutput, Waveform,
+};
+use ggez::{
+    event, graphics,
+    input::keyboard::{KeyCode, KeyMods},
+    Context, GameResult,
+};
+use ggez_egui::{egui, EguiBackend};
+use std::fs;
+use std::path::PathBuf;
+
+const FPS: usize = 60;
+
+const SCREEN_SIZE: (f32, f32) = (800.0, 400.0);
+
+// Number of save state slots, bound to the F1-F4 keys
+const SAVE_SLOTS: usize = 4;
+const SLOT_KEYS: [KeyCode; SAVE_SLOTS] = [KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4];
+
+// Emulated time after which run to cursor gives up
+const RUN_TO_SECONDS: usize = 10;
+
+// Runs the game backwards while held
+const REWIND_KEY: KeyCode = KeyCode::Back;
+
+// What each colour of a palette is used for
+const COLOR_LABELS: [&str; 4] = ["Background", "Plane 1", "Plane 2", "Both planes"];
+
+// Keys from 0-F that are used to emulate the
+// 16-key chip-8 keyboard
+const KEYS: [KeyCode; 16] = [
+    KeyCode::X,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Q,
+    KeyCode::W,
+    KeyCode::E,
+    KeyCode::A,
+    KeyCode::S,
+    KeyCode::D,
+    KeyCode::Z,
+    KeyCode::C,
+    KeyCode::Key4,
+    KeyCode::R,
+    KeyCode::F,
+    KeyCode::V,
+];
+
+struct GameState {
+    egui_backend: EguiBackend,
+    cpu: Cpu,
+    // Path of the ROM that is being run
+    rom: String,
+    // Number of CPU cycles/ticks executed
+    cycles: u128,
+    // Step through CPU ticks, one a the time
+    show_menu: bool,
+    // Set when the CPU faults, halting emulation
+    // until the ROM is restarted
+    error: Option<Chip8Error>,
+    // Result of the last save state action, shown in the menu
+    status: Option<String>,
+    // Set by the Pause button, no frames are run while paused
+    paused: bool,
+    // History of the last frames, used for rewinding
+    rewind: Rewind,
+    // Seconds of history kept, changed from the menu
+    rewind_seconds: usize,
+    // Set while the rewind key is held
+    rewinding: bool,
+    // Keys held on the keyboard, copied to the CPU at the start of every frame
+    input: Keypad,
+    // File that movies are recorded to and played back from
+    movie_path: String,
+    // The movie being recorded
+    recording: Option<Movie>,
+    // The movie being played back, and the next frame to play
+    playback: Option<(Movie, usize)>,
+    debugger: Debugger,
+    memory_viewer: MemoryViewer,
+    sprite_viewer: SpriteViewer,
+    // Renders the sound of the buzzer
+    buzzer: Buzzer,
+    // Draws the CHIP-8 screen
+    renderer: Renderer,
+    // File the settings are kept in between runs
+    config_path: PathBuf,
+    // Text of the hex colour fields of the palette editor
+    palette_hex: [String; 4],
+    // Set while the palette has changes that are not saved yet
+    palette_unsaved: bool,
+    // Sound device, or nothing if there is none
+    speaker: Box<dyn AudioOutput>,
+    // File that the audio is recorded to
+    wav_path: String,
+    // The audio recording, written as frames are run
+    wav: Option<WavOutput>,
+}
+
+impl GameState {
+    fn new(rom: &str, options: &RunOptions) -> GameResult<Self> {
+        let mut builder = Cpu::builder(options.clock_speed)
+            .quirks(options.quirks)
+            .xo_chip(options.xo_chip);
+        if let Some(seed) = options.seed {
+            builder = builder.seed(seed);
+        }
+        let mut cpu = builder.build();
+        cpu.load_rom(rom).map_err(|e| {
+            ggez::GameError::ResourceLoadError(format!("Failed to load ROM {}: {}", rom, e))
+        })?;
+        #[cfg(feature = "debug")]
+        {
+            cpu.tracer = options.trace.open().map_err(ggez::GameError::CustomError)?;
+        }
+        let config_path = Config::default_path();
+        let (config, status) = match Config::load(&config_path) {
+            Ok(config) => (config, None),
+            Err(e) => (
+                Config::default(),
+                Some(format!("Failed to read {}: {}", config_path.display(), e)),
+            ),
+        };
+        let mut renderer = Renderer::new();
+        renderer.palette = config.palette;
+        let mut state = GameState {
+            egui_backend: EguiBackend::default(),
+            cpu,
+            rom: rom.to_string(),
+            cycles: 0,
+            show_menu: false,
+            error: None,
+            status,
+            paused: false,
+            rewind: Rewind::new(options.rewind_seconds, FPS, options.rewind_budget),
+            rewind_seconds: options.rewind_seconds,
+            rewinding: false,
+            input: Keypad::new(),
+            movie_path: options
+                .record
+                .clone()
+                .or_else(|| options.replay.clone())
+                .unwrap_or(format!("{}.movie", rom)),
+            recording: None,
+            playback: None,
+            debugger: Debugger::default(),
+            memory_viewer: MemoryViewer::default(),
+            sprite_viewer: SpriteViewer::default(),
+            buzzer: Buzzer::new(BuzzerSettings::default(), DEFAULT_SAMPLE_RATE),
+            renderer,
+            palette_hex: config.palette.colors.map(format_color),
+            palette_unsaved: false,
+            config_path,
+            speaker: match Speaker::new(DEFAULT_SAMPLE_RATE) {
+                Some(speaker) => Box::new(speaker),
+                None => Box::new(NullOutput),
+            },
+            wav_path: format!("{}.wav", rom),
+            wav: None,
+        };
+        if options.replay.is_some() {
+            state.start_replay();
+        } else if options.record.is_some() {
+            state.start_recording();
+        }
+        Ok(state)
+    }
+
+    // Runs one frame of emulation. The keypad is sampled once, at the start
+    // of the frame, from the movie being played back or from the keyboard
+    fn run_frame(&mut self) {
+        self.cpu.keypad = match self.next_movie_frame() {
+            Some(keypad) => keypad,
+            None => self.input,
+        };
+        if let Some(movie) = &mut self.recording {
+            movie.record(&self.cpu.keypad);
+        }
+        // A movie only holds whole frames, so breakpoints, which stop
+        // partway through one, are left out while a movie runs
+        let breakpoints = self
+            .movie_running()
+            .then(|| std::mem::take(&mut self.cpu.breakpoints));
+        let buzzer = &mut self.buzzer;
+        let result = self.cpu.run_frame_with(|cpu| buzzer.cycle(cpu));
+        if let Some(breakpoints) = breakpoints {
+            self.cpu.breakpoints = breakpoints;
+        }
+        match result {
+            Ok(StepOutcome::Break(reason)) => self.stop_at(reason),
+            Ok(_) => {}
+            Err(e) => self.fault(e),
+        }
+        match &mut self.wav {
+            Some(wav) => self.buzzer.flush(&mut Tee(self.speaker.as_mut(), wav)),
+            None => self.buzzer.flush(self.speaker.as_mut()),
+        }
+        self.cycles += self.cpu.cycles_per_frame() as u128;
+        if self.error.is_none() {
+            self.rewind.push(self.cpu.save_state());
+        }
+    }
+
+    // Halts the emulation until the ROM is restarted
+    fn fault(&mut self, e: Chip8Error) {
+        self.cpu.core_dump();
+        self.error = Some(e);
+    }
+
+    // Pauses the emulation at a breakpoint and shows it in the debugger
+    fn stop_at(&mut self, reason: BreakReason) {
+        self.paused = true;
+        self.debugger.open = true;
+        self.status = Some(reason.to_string());
+    }
+
+    // Carries out a step requested from the debugger. Anything
+    // but whole frames would make a movie impossible to replay
+    fn debug(&mut self, action: DebugAction) {
+        match action {
+            DebugAction::AddBreakpoint(breakpoint) => {
+                if !self.movie_active() {
+                    self.cpu.breakpoints.push(breakpoint);
+                }
+                return;
+            }
+            // The list may have changed since the action was made
+            DebugAction::RemoveBreakpoint(n) => {
+                if !self.movie_active() && n < self.cpu.breakpoints.len() {
+                    self.cpu.breakpoints.remove(n);
+                }
+                return;
+            }
+            _ => {}
+        }
+        if self.error.is_some() || (action != DebugAction::StepFrame && self.movie_active()) {
+            return;
+        }
+        self.cpu.keypad = self.input;
+        match action {
+            DebugAction::StepInstruction => match self.cpu.tick() {
+                Ok(StepOutcome::Break(reason)) => self.stop_at(reason),
+                Ok(_) => {}
+                Err(e) => self.fault(e),
+            },
+            DebugAction::StepFrame => self.run_frame(),
+            // Runs a whole subroutine, stopping at the instruction after the call
+            DebugAction::StepOver => {
+                let pc = self.cpu.pc();
+                match debugger::decode_at(self.cpu.memory(), pc) {
+                    Some((_, Instruction::Call(_))) => self.run_to(pc.wrapping_add(2)),
+                    _ => self.debug(DebugAction::StepInstruction),
+                }
+            }
+            DebugAction::RunTo(addr) => self.run_to(addr),
+            DebugAction::AddBreakpoint(_) | DebugAction::RemoveBreakpoint(_) => {}
+        }
+    }
+
+    // Writes a byte edited in the memory viewer to RAM. Like stepping,
+    // edits would make a movie impossible to replay
+    fn poke(&mut self, addr: u16, value: u8) {
+        if self.movie_active() {
+            return;
+        }
+        if let Err(e) = self.cpu.write_memory(addr as usize, &[value]) {
+            self.status = Some(e.to_string());
+        }
+    }
+
+    // Runs until PC reaches addr, giving up after RUN_TO_SECONDS of emulated time
+    fn run_to(&mut self, addr: u16) {
+        for _ in 0..self.cpu.clock_speed * RUN_TO_SECONDS {
+            match self.cpu.tick() {
+                Ok(StepOutcome::Exited) => return,
+                Ok(StepOutcome::Break(reason)) => return self.stop_at(reason),
+                Ok(_) if self.cpu.pc() == addr => return,
+                Ok(_) => {}
+                Err(e) => return self.fault(e),
+            }
+        }
+        self.status = Some(format!("{:#05X} was not reached", addr));
+    }
+
+    // Returns the keypad state of the next frame of the movie being
+    // played back, ending the playback after the last frame
+    fn next_movie_frame(&mut self) -> Option<Keypad> {
+        let (movie, frame) = self.playback.as_mut()?;
+        let keypad = movie.frames.get(*frame).copied();
+        *frame += 1;
+        if keypad.is_none() {
+            self.playback = None;
+            self.status = Some("Replay finished".to_string());
+        }
+        keypad
+    }
+
+    // Restarts the ROM and records every frame from then on
+    fn start_recording(&mut self) {
+        self.stop_movie();
+        self.restart();
+        self.recording = Some(Movie::new(&self.cpu));
+        self.status = Some(format!("Recording to {}", self.movie_path));
+    }
+
+    // Restarts the ROM with the settings of the movie and plays it back
+    fn start_replay(&mut self) {
+        self.stop_movie();
+        let result = Movie::load(&self.movie_path)
+            .map_err(|e| format!("Failed to read {}: {}", self.movie_path, e))
+            .and_then(|movie| {
+                let rom = fs::read(&self.rom).map_err(|e| e.to_string())?;
+                movie
+                    .start(&mut self.cpu, &rom)
+                    .map_err(|e| e.to_string())?;
+                Ok(movie)
+            });
+        match result {
+            Ok(movie) => {
+                self.error = None;
+                self.rewind.clear();
+                self.playback = Some((movie, 0));
+                self.status = Some(format!("Replaying {}", self.movie_path));
+            }
+            Err(e) => self.status = Some(e),
+        }
+    }
+
+    // Ends the playback, or ends the recording and writes it to disk
+    fn stop_movie(&mut self) {
+        self.playback = None;
+        if let Some(movie) = self.recording.take() {
+            self.status = Some(match movie.save(&self.movie_path) {
+                Ok(()) => format!("Saved {} frames to {}", movie.frames.len(), self.movie_path),
+                Err(e) => format!("Failed to write {}: {}", self.movie_path, e),
+            });
+        }
+    }
+
+    // Starts recording the audio, or ends the recording and completes the file.
+    // Only frames that are run are recorded, so pausing leaves no gap
+    fn toggle_wav(&mut self) {
+        self.status = Some(match self.wav.take() {
+            Some(wav) => match wav.finish() {
+                Ok(()) => format!("Saved audio to {}", self.wav_path),
+                Err(e) => format!("Failed to write {}: {}", self.wav_path, e),
+            },
+            None => match WavOutput::create(&self.wav_path, self.buzzer.sample_rate()) {
+                Ok(wav) => {
+                    self.wav = Some(wav);
+                    format!("Recording audio to {}", self.wav_path)
+                }
+                Err(e) => format!("Failed to create {}: {}", self.wav_path, e),
+            },
+        });
+    }
+
+    // Keeps the palette for the next run
+    fn save_palette(&mut self) {
+        self.palette_unsaved = false;
+        let config = Config {
+            palette: self.renderer.palette,
+        };
+        if let Err(e) = config.save(&self.config_path) {
+            self.status = Some(format!(
+                "Failed to write {}: {}",
+                self.config_path.display(),
+                e
+            ));
+        }
+    }
+
+    fn movie_running(&self) -> bool {
+        self.recording.is_some() || self.playback.is_some()
+    }
+
+    // Rewinding and loading states would make the movie impossible to replay
+    fn movie_active(&mut self) -> bool {
+        let active = self.movie_running();
+        if active {
+            self.status = Some("Stop the movie first".to_string());
+        }
+        active
+    }
+
+    // Reloads the ROM and clears any CPU fault
+    fn restart(&mut self) {
+        self.cpu.reset();
+        if let Err(e) = self.cpu.load_rom(&self.rom) {
+            self.status = Some(format!("Failed to load {}: {}", self.rom, e));
+        }
+        self.error = None;
+        self.rewind.clear();
+    }
+
+    // Steps back to the previous recorded frame
+    fn step_back(&mut self) {
+        if self.movie_active() {
+            return;
+        }
+        if let Some(state) = self.rewind.pop() {
+            match self.cpu.load_state(&state) {
+                Ok(()) => self.error = None,
+                Err(e) => self.status = Some(format!("Failed to rewind: {}", e)),
+            }
+        }
+    }
+
+    // Save states are stored next to the ROM, one file per slot
+    fn slot_path(&self, slot: usize) -> String {
+        format!("{}.{}.state", self.rom, slot)
+    }
+
+    // Writes the CPU state to a save slot
+    fn save_slot(&mut self, slot: usize) {
+        let path = self.slot_path(slot);
+        self.status = Some(match fs::write(&path, self.cpu.save_state()) {
+            Ok(()) => format!("Saved slot {}", slot),
+            Err(e) => format!("Failed to write {}: {}", path, e),
+        });
+    }
+
+    // Restores the CPU state from a save slot, clearing any CPU fault
+    fn load_slot(&mut self, slot: usize) {
+        if self.movie_active() {
+            return;
+        }
+        let path = self.slot_path(slot);
+        let result = fs::read(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))
+            .and_then(|data| self.cpu.load_state(&data).map_err(|e| e.to_string()));
+        self.status = Some(match result {
+            Ok(()) => {
+                self.error = None;
+                format!("Loaded slot {}", slot)
+            }
+            Err(e) => e,
+        });
+    }
+
+    // Draws the egui window
+    fn draw_egui(&mut self, ctx: &mut Context) -> GameResult {
+        let egui_ctx = self.egui_backend.ctx();
+        let mut restart = false;
+        let mut save = None;
+        let mut load = None;
+        let mut step_back = false;
+        let mut record = false;
+        let mut replay = false;
+        let mut stop = false;
+        let mut toggle_wav = false;
+        let mut palette_changed = false;
+        // The movie replays with the settings it was recorded with,
+        // so they can not change while one is running
+        let movie = self.movie_running();
+        let pointer_down = egui_ctx.input().pointer.any_down();
+        let mut debug = None;
+        let mut poke = None;
+        egui::Window::new("Options")
+            .open(&mut self.show_menu)
+            .show(&egui_ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Pause").clicked() {
+                        self.paused = true;
+                    }
+                    if ui.button("Play").clicked() {
+                        self.paused = false;
+                    }
+                    // Rewinds a single frame, mostly useful while paused
+                    if ui.button("Step back").clicked() {
+                        step_back = true;
+                    }
+                    if ui.button("Restart").clicked() {
+                        restart = true;
+                    }
+                });
+                ui.separator();
+                ui.label("CPU Clock Speed:");
+                // Slider that changes the clock speed of the emulation
+                // thus speeding up or slowing down the game
+                ui.add_enabled(
+                    !movie,
+                    egui::Slider::new(&mut self.cpu.clock_speed, 50..=2000),
+                );
+                ui.label(format!("Rewind history ({} frames):", self.rewind.len()));
+                if ui
+                    .add(egui::Slider::new(&mut self.rewind_seconds, 0..=60).suffix(" s"))
+                    .changed()
+                {
+                    self.rewind.set_depth(self.rewind_seconds * FPS);
+                }
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.debugger.open, "Debugger");
+                    ui.checkbox(&mut self.memory_viewer.open, "Memory");
+                    ui.checkbox(&mut self.sprite_viewer.open, "Sprites");
+                });
+                ui.separator();
+                // Changing the memory size requires reloading the ROM
+                let mut xo_chip = self.cpu.is_xo_chip();
+                if ui
+                    .add_enabled(!movie, egui::Checkbox::new(&mut xo_chip, "XO-CHIP"))
+                    .changed()
+                {
+                    self.cpu.set_xo_chip(xo_chip);
+                    restart = true;
+                }
+                // Behaviour of the ambiguous opcodes, different
+                // ROMs expect different interpreters
+                ui.collapsing("Quirks", |ui| {
+                    ui.add_enabled_ui(!movie, |ui| {
+                        let quirks = &mut self.cpu.quirks;
+                        let selected = quirks.preset().map_or("Custom", |p| p.name());
+                        egui::ComboBox::from_label("Preset")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                for preset in QuirksPreset::ALL {
+                                    let active = quirks.preset() == Some(preset);
+                                    if ui.selectable_label(active, preset.name()).clicked() {
+                                        *quirks = preset.quirks();
+                                    }
+                                }
+                            });
+                        ui.checkbox(&mut quirks.shift_uses_vy, "Shift uses Vy");
+                        ui.checkbox(
+                            &mut quirks.load_store_increments_i,
+                            "Load/store increments I",
+                        );
+                        ui.add_enabled(
+                            quirks.load_store_increments_i,
+                            egui::Checkbox::new(
+                                &mut quirks.load_store_increments_by_x,
+                                "... by x only",
+                            ),
+                        );
+                        ui.checkbox(&mut quirks.jump_uses_vx, "Jump uses Vx");
+                        ui.checkbox(&mut quirks.vf_reset, "Logic ops reset VF");
+                        ui.checkbox(&mut quirks.clip_sprites, "Clip sprites");
+                        ui.checkbox(&mut quirks.display_wait, "Display wait");
+                    })
+                });
+                // Anti-flicker filters, only changing what is drawn
+                ui.collapsing("Display", |ui| {
+                    let renderer = &mut self.renderer;
+                    egui::ComboBox::from_label("Filter")
+                        .selected_text(renderer.filter.name())
+                        .show_ui(ui, |ui| {
+                            for filter in Filter::ALL {
+                                ui.selectable_value(&mut renderer.filter, filter, filter.name());
+                            }
+                        });
+                    match renderer.filter {
+                        Filter::Phosphor => {
+                            ui.add(
+                                egui::Slider::new(&mut renderer.decay, 0.0..=0.95).text("Decay"),
+                            );
+                        }
+                        Filter::Blend => {
+                            ui.add(
+                                egui::Slider::new(&mut renderer.blend_frames, 2..=MAX_BLEND_FRAMES)
+                                    .text("Frames"),
+                            );
+                        }
+                        Filter::Off => {}
+                    }
+                });
+                ui.collapsing("Palette", |ui| {
+                    let palette = &mut self.renderer.palette;
+                    let before = *palette;
+                    let selected = palette.preset().map_or("Custom", |preset| preset.name());
+                    egui::ComboBox::from_label("Preset")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            for preset in PalettePreset::ALL {
+                                let current = palette.preset() == Some(preset);
+                                if ui.selectable_label(current, preset.name()).clicked() {
+                                    *palette = preset.palette();
+                                }
+                            }
+                        });
+                    for (n, label) in COLOR_LABELS.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_srgb(&mut palette.colors[n]);
+                            let hex = &mut self.palette_hex[n];
+                            let response =
+                                ui.add(egui::TextEdit::singleline(hex).desired_width(70.0));
+                            if response.lost_focus() {
+                                if let Ok(color) = parse_color(hex) {
+                                    palette.colors[n] = color;
+                                }
+                                *hex = format_color(palette.colors[n]);
+                            }
+                            ui.label(*label);
+                        });
+                    }
+                    palette_changed = *palette != before;
+                });
+                ui.collapsing("Sound", |ui| {
+                    let settings = &mut self.buzzer.settings;
+                    ui.checkbox(&mut settings.muted, "Mute");
+                    egui::ComboBox::from_label("Waveform")
+                        .selected_text(settings.waveform.name())
+                        .show_ui(ui, |ui| {
+                            for waveform in Waveform::ALL {
+                                ui.selectable_value(
+                                    &mut settings.waveform,
+                                    waveform,
+                                    waveform.name(),
+                                );
+                            }
+                        });
+                    ui.add(
+                        egui::Slider::new(&mut settings.frequency, 110.0..=1760.0)
+                            .logarithmic(true)
+                            .suffix(" Hz")
+                            .text("Frequency"),
+                    );
+                    ui.add(egui::Slider::new(&mut settings.volume, 0.0..=1.0).text("Volume"));
+                    ui.text_edit_singleline(&mut self.wav_path);
+                    let label = if self.wav.is_some() {
+                        "Stop recording"
+                    } else {
+                        "Record WAV"
+                    };
+                    if ui.button(label).clicked() {
+                        toggle_wav = true;
+                    }
+                });
+                // Save states, also bound to F1-F4 and Shift+F1-F4
+                ui.collapsing("Save states", |ui| {
+                    for slot in 1..=SAVE_SLOTS {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Slot {}", slot));
+                            if ui.button("Save").clicked() {
+                                save = Some(slot);
+                            }
+                            if ui.button("Load").clicked() {
+                                load = Some(slot);
+                            }
+                        });
+                    }
+                });
+                // Input recording, replayed frame by frame
+                ui.collapsing("Movie", |ui| {
+                    ui.text_edit_singleline(&mut self.movie_path);
+                    ui.horizontal(|ui| {
+                        if ui.button("Record").clicked() {
+                            record = true;
+                        }
+                        if ui.button("Replay").clicked() {
+                            replay = true;
+                        }
+                        if ui.button("Stop").clicked() {
+                            stop = true;
+                        }
+                    });
+                });
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+                if ui.button("Quit").clicked() {
+                    ggez::event::quit(ctx)
+                }
+            });
+        if self.debugger.open {
+            debug = self.debugger.show(&egui_ctx, &self.cpu, self.paused);
+        }
+        if self.memory_viewer.open {
+            poke = self.memory_viewer.show(&egui_ctx, &self.cpu, self.paused);
+        }
+        if self.sprite_viewer.open {
+            self.sprite_viewer.show(&egui_ctx, &self.cpu);
+        }
+        // Dialog shown when the CPU has faulted
+        if let Some(e) = self.error {
+            egui::Window::new("Error")
+                .collapsible(false)
+                .show(&egui_ctx, |ui| {
+                    ui.label(format!("The emulator stopped: {}", e));
+                    if ui.button("Restart").clicked() {
+                        restart = true;
+                    }
+                });
+        }
+        if restart {
+            self.stop_movie();
+            self.restart();
+        }
+        if step_back {
+            self.step_back();
+        }
+        if let Some(slot) = save {
+            self.save_slot(slot);
+        }
+        if let Some(slot) = load {
+            self.load_slot(slot);
+        }
+        if record {
+            self.start_recording();
+        }
+        if replay {
+            self.start_replay();
+        }
+        if stop {
+            self.stop_movie();
+        }
+        if toggle_wav {
+            self.toggle_wav();
+        }
+        if palette_changed {
+            self.palette_hex = self.renderer.palette.colors.map(format_color);
+            self.palette_unsaved = true;
+        }
+        // Dragging in a colour picker changes the palette every frame,
+        // so it is only saved once the mouse button is released
+        if self.palette_unsaved && !pointer_down {
+            self.save_palette();
+        }
+        if let Some(action) = debug {
+            self.debug(action);
+        }
+        if let Some((addr, value)) = poke {
+            self.poke(addr, value);
+        }
+        Ok(())
+    }
+}
+
+// Implementations of the required ggez methods
+impl event::EventHandler<ggez::GameError> for GameState {
+    // Updates the state by running the CPU in whole 60 Hz frames,
+    // or stepping back through the history while rewinding
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        while ggez::timer::check_update_time(ctx, FPS as u32) {
+            if self.rewinding {
+                self.step_back();
+            } else if !self.paused && self.error.is_none() {
+                self.run_frame();
+            } else {
+                continue;
+            }
+            // Paused screens stay as they are, afterglow included
+            self.renderer.frame(&self.cpu.display);
+        }
+        self.draw(ctx)?;
+        self.draw_egui(ctx)?;
+        ctx.timer_context.tick();
+        Ok(())
+    }
+
+    /// draw is where we should actually render the game's current state.
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        // Clears the frame to the background colour of the palette,
+        // then draws the CHIP-8 screen with the GUI on top
+        graphics::clear(ctx, self.renderer.palette.rgba(0).into());
+        self.renderer.draw(ctx, &self.cpu.display, SCREEN_SIZE)?;
+        graphics::draw(ctx, &self.egui_backend, graphics::DrawParam::default())?;
+        graphics::present(ctx)?;
+        ggez::timer::yield_now();
+        Ok(())
+    }
+
+    /// key_down_event gets fired when a key gets pressed.
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyCode,
+        keymods: KeyMods,
+        _repeat: bool,
+    ) {
+        self.egui_backend.input.key_down_event(keycode, keymods);
+        match keycode {
+            // Toggles the menu
+            KeyCode::Return => {
+                self.show_menu = !self.show_menu;
+            }
+            REWIND_KEY => {
+                self.rewinding = true;
+            }
+            // F1-F4 save to a slot, and load from it with Shift held
+            _ if SLOT_KEYS.contains(&keycode) => {
+                let slot = SLOT_KEYS.iter().position(|k| k == &keycode).unwrap() + 1;
+                if keymods.contains(KeyMods::SHIFT) {
+                    self.load_slot(slot);
+                } else {
+                    self.save_slot(slot);
+                }
+            }
+            _ => {
+                // Lets the CPU know that a key is pressed
+                for (i, key) in KEYS.iter().enumerate() {
+                    if key == &keycode {
+                        self.input.press(i);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods) {
+        if keycode == REWIND_KEY {
+            self.rewinding = false;
+        }
+        for (i, key) in KEYS.iter().enumerate() {
+            if key == &keycode {
+                self.input.release(i);
+                return;
+            }
+        }
+    }
+
+    // Input methods required for the egui GUI elements
+    fn resize_event(&mut self, ctx: &mut ggez::Context, width: f32, height: f32) {
+        self.egui_backend.input.resize_event(width, height);
+        let rect = ggez::graphics::Rect::new(0.0, 0.0, width, height);
+        ggez::graphics::set_screen_coordinates(ctx, rect).unwrap();
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut ggez::Context,
+        button: ggez::event::MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        self.egui_backend.input.mouse_button_up_event(button);
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut ggez::Context,
+        button: ggez::event::MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        self.egui_backend.input.mouse_button_down_event(button);
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut ggez::Context, x: f32, y: f32) {
+        self.egui_backend.input.mouse_wheel_event(x, y);
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut ggez::Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        self.egui_backend.input.mouse_motion_event(x, y);
+    }
+
+    // Saves a palette that is still being edited when the window closes
+    fn quit_event(&mut self, _ctx: &mut ggez::Context) -> bool {
+        if self.palette_unsaved {
+            self.save_palette();
+        }
+        false
+    }
+}
+
+pub fn run(options: &RunOptions) -> GameResult {
+    let rom = options.rom.as_deref().unwrap_or(DEFAULT_ROM);
+    let (ctx, events_loop) = ggez::ContextBuilder::new("chip8", "Fredrik Reinholdsen")
+        .window_setup(ggez::conf::WindowSetup::default().title("CHIP-8 Emulator"))
+        .window_mode(
+            ggez::conf::WindowMode::default()
+                .dimensions(SCREEN_SIZE.0 as f32, SCREEN_SIZE.1 as f32),
+        )
+        .build()?;
+
+    // Initialize game state struct and start running game
+    let state = GameState::new(rom, options)?;
+    event::run(ctx, events_loop, state)
+}
@@ -0,0 +1,218 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Runs a ROM without a window, for a fixed number of
+ *                  frames with keys fed from a script, and prints the
+ *                  final screen and registers. The buzzer can be
+ *                  recorded to a WAV file, timed by the emulated cycles
+ *                  so that the same run always gives the same audio.
+ *                  Used on machines with no display, such as CI
+ *                  servers. Also serves GDB when debugging a ROM with
+ *                  an external debugger.
+ */
+use crate::RunOptions;
+use chip8::audio::DEFAULT_SAMPLE_RATE;
+use chip8::{
+    AudioOutput, Buzzer, BuzzerSettings, Chip8Display, Cpu, GdbStub, Keypad, NullOutput,
+    StepOutcome, WavOutput,
+};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::net::TcpListener;
+
+// Grey level of each pixel colour in PNG screenshots
+const PNG_LEVELS: [u8; 4] = [0x00, 0xFF, 0xAA, 0x55];
+
+// A change of the held keys, taking effect at the start of a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct KeyEvent {
+    frame: usize,
+    key: usize,
+    pressed: bool,
+}
+
+// Parses an input script. Every line holds a frame number, press or
+// release, and one or more hex key digits. '#' starts a comment:
+//
+//     # Hold 5 for a second, then tap A
+//     0   press 5
+//     60  release 5
+//     61  press a
+//     62  release a
+fn parse_script(source: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut events = vec![];
+    for (n, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let frame = match words.next() {
+            Some(word) => word,
+            None => continue,
+        };
+        let error = |message: String| format!("line {}: {}", n + 1, message);
+        let frame = frame
+            .parse()
+            .map_err(|_| error(format!("Invalid frame number {}", frame)))?;
+        let pressed = match words.next() {
+            Some("press") => true,
+            Some("release") => false,
+            _ => return Err(error("Expected press or release".to_string())),
+        };
+        let mut keys = words.peekable();
+        if keys.peek().is_none() {
+            return Err(error("Expected a key".to_string()));
+        }
+        for key in keys {
+            let key = match usize::from_str_radix(key, 16) {
+                Ok(key) if key <= 0xF => key,
+                _ => return Err(error(format!("Invalid key {}", key))),
+            };
+            events.push(KeyEvent {
+                frame,
+                key,
+                pressed,
+            });
+        }
+    }
+    events.sort_by_key(|event| event.frame);
+    Ok(events)
+}
+
+// Writes the screen as a greyscale PNG, one image pixel per display pixel
+fn write_png(display: &Chip8Display, path: &str) -> Result<(), String> {
+    let error = |e: &dyn std::fmt::Display| format!("Failed to write {}: {}", path, e);
+    let file = File::create(path).map_err(|e| error(&e))?;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        display.width() as u32,
+        display.height() as u32,
+    );
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let pixels: Vec<u8> = display
+        .screen()
+        .iter()
+        .map(|&p| PNG_LEVELS[p as usize])
+        .collect();
+    let mut writer = encoder.write_header().map_err(|e| error(&e))?;
+    writer.write_image_data(&pixels).map_err(|e| error(&e))
+}
+
+// Prints the registers, timers and stack of the CPU
+fn print_registers(cpu: &Cpu) {
+    for (n, v) in cpu.registers().iter().enumerate() {
+        print!("V{:X}={:02X}{}", n, v, if n % 8 == 7 { "\n" } else { " " });
+    }
+    println!(
+        "PC={:04X} I={:04X} DT={:02X} ST={:02X} CYCLE={}",
+        cpu.pc(),
+        cpu.i(),
+        cpu.timers().delay(),
+        cpu.timers().sound(),
+        cpu.cycle
+    );
+    let stack: Vec<String> = cpu.stack().iter().map(|a| format!("{:04X}", a)).collect();
+    println!("STACK=[{}]", stack.join(" "));
+}
+
+// Sets up a CPU from the run options and loads the ROM into it
+fn load(options: &RunOptions) -> Result<Cpu, String> {
+    let rom_path = options.rom.as_deref().ok_or("Headless mode needs a ROM")?;
+    let rom = fs::read(rom_path).map_err(|e| format!("Failed to read ROM {}: {}", rom_path, e))?;
+    let mut builder = Cpu::builder(options.clock_speed)
+        .quirks(options.quirks)
+        .xo_chip(options.xo_chip);
+    if let Some(seed) = options.seed {
+        builder = builder.seed(seed);
+    }
+    let mut cpu = builder.build();
+    cpu.load_bytes(&rom).map_err(|e| e.to_string())?;
+    #[cfg(feature = "debug")]
+    {
+        cpu.tracer = options.trace.open()?;
+    }
+    Ok(cpu)
+}
+
+// Runs the ROM for the given number of frames at a virtual
+// clock, then prints the screen and the registers to stdout
+pub fn run(options: &RunOptions) -> Result<(), String> {
+    let mut cpu = load(options)?;
+    let events = match &options.input {
+        Some(path) => {
+            let source = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read input script {}: {}", path, e))?;
+            parse_script(&source).map_err(|e| format!("{}:{}", path, e))?
+        }
+        None => vec![],
+    };
+
+    let mut wav = match &options.wav {
+        Some(path) => Some(
+            WavOutput::create(path, DEFAULT_SAMPLE_RATE)
+                .map_err(|e| format!("Failed to create {}: {}", path, e))?,
+        ),
+        None => None,
+    };
+    let mut buzzer = Buzzer::new(BuzzerSettings::default(), DEFAULT_SAMPLE_RATE);
+
+    let mut keypad = Keypad::new();
+    let mut events = events.iter().peekable();
+    let mut result = Ok(());
+    for frame in 0..options.frames {
+        while let Some(event) = events.next_if(|event| event.frame <= frame) {
+            if event.pressed {
+                keypad.press(event.key);
+            } else {
+                keypad.release(event.key);
+            }
+        }
+        cpu.keypad = keypad;
+        let outcome = cpu.run_frame_with(|cpu| buzzer.cycle(cpu));
+        let output: &mut dyn AudioOutput = match &mut wav {
+            Some(wav) => wav,
+            None => &mut NullOutput,
+        };
+        buzzer.flush(output);
+        match outcome {
+            Ok(StepOutcome::Exited) => break,
+            Ok(_) => {}
+            Err(e) => {
+                result = Err(format!("Frame {}: {}", frame, e));
+                break;
+            }
+        }
+    }
+
+    print!("{}", cpu.display);
+    print_registers(&cpu);
+    if let Some(path) = &options.png {
+        write_png(&cpu.display, path)?;
+    }
+    if let (Some(wav), Some(path)) = (wav, &options.wav) {
+        wav.finish()
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    #[cfg(feature = "debug")]
+    if let Some(tracer) = &mut cpu.tracer {
+        tracer
+            .finish()
+            .map_err(|e| format!("Failed to write trace: {}", e))?;
+    }
+    result
+}
+
+// Waits for GDB to connect on a local port and lets it control the ROM,
+// printing the screen and registers once it detaches
+pub fn serve_gdb(options: &RunOptions, port: u16) -> Result<(), String> {
+    let mut cpu = load(options)?;
+    let error = |e: std::io::Error| format!("GDB connection on port {}: {}", port, e);
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(error)?;
+    eprintln!("Waiting for GDB on 127.0.0.1:{}", port);
+    let mut stub = GdbStub::accept(&listener).map_err(error)?;
+    stub.serve(&mut cpu).map_err(error)?;
+    print!("{}", cpu.display);
+    print_registers(&cpu);
+    Ok(())
+}
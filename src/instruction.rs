@@ -0,0 +1,376 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Decodes raw 16-bit opcodes into typed CHIP-8
+ *                  instructions, separate from their execution.
+ */
+use std::fmt;
+
+// Register indices are 4-bit values (0x0 - 0xF),
+// addresses are 12-bit values (0x000 - 0xFFF)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// 0nnn - Jump to a machine code routine at nnn
+    Sys(u16),
+    /// 00E0 - Clear the display
+    Cls,
+    /// 00EE - Return from a subroutine
+    Ret,
+    /// 1nnn - Jump to address nnn
+    Jp(u16),
+    /// 2nnn - Call subroutine at nnn
+    Call(u16),
+    /// 3xkk - Skip next instruction if Vx == kk
+    SeByte { x: u8, kk: u8 },
+    /// 4xkk - Skip next instruction if Vx != kk
+    SneByte { x: u8, kk: u8 },
+    /// 5xy0 - Skip next instruction if Vx == Vy
+    SeReg { x: u8, y: u8 },
+    /// 6xkk - Set Vx = kk
+    LdByte { x: u8, kk: u8 },
+    /// 7xkk - Set Vx = Vx + kk
+    AddByte { x: u8, kk: u8 },
+    /// 8xy0 - Set Vx = Vy
+    LdReg { x: u8, y: u8 },
+    /// 8xy1 - Set Vx = Vx OR Vy
+    Or { x: u8, y: u8 },
+    /// 8xy2 - Set Vx = Vx AND Vy
+    And { x: u8, y: u8 },
+    /// 8xy3 - Set Vx = Vx XOR Vy
+    Xor { x: u8, y: u8 },
+    /// 8xy4 - Set Vx = Vx + Vy, Vf = carry
+    AddReg { x: u8, y: u8 },
+    /// 8xy5 - Set Vx = Vx - Vy, Vf = NOT borrow
+    Sub { x: u8, y: u8 },
+    /// 8xy6 - Set Vx = Vx SHR 1, Vf = shifted out bit
+    Shr { x: u8, y: u8 },
+    /// 8xy7 - Set Vx = Vy - Vx, Vf = NOT borrow
+    Subn { x: u8, y: u8 },
+    /// 8xyE - Set Vx = Vx SHL 1, Vf = shifted out bit
+    Shl { x: u8, y: u8 },
+    /// 9xy0 - Skip next instruction if Vx != Vy
+    SneReg { x: u8, y: u8 },
+    /// Annn - Set I = nnn
+    LdI(u16),
+    /// Bnnn - Jump to address nnn + V0
+    JpV0(u16),
+    /// Cxkk - Set Vx = random byte AND kk
+    Rnd { x: u8, kk: u8 },
+    /// Dxyn - Draw n-byte sprite from I at (Vx, Vy), Vf = collision
+    Drw { x: u8, y: u8, n: u8 },
+    /// Ex9E - Skip next instruction if key Vx is pressed
+    Skp { x: u8 },
+    /// ExA1 - Skip next instruction if key Vx is not pressed
+    Sknp { x: u8 },
+    /// Fx07 - Set Vx = delay timer
+    LdVxDt { x: u8 },
+    /// Fx0A - Wait for a key press and store it in Vx
+    LdKey { x: u8 },
+    /// Fx15 - Set delay timer = Vx
+    LdDtVx { x: u8 },
+    /// Fx18 - Set sound timer = Vx
+    LdStVx { x: u8 },
+    /// Fx1E - Set I = I + Vx
+    AddI { x: u8 },
+    /// Fx29 - Set I = location of the font sprite for digit Vx
+    LdFont { x: u8 },
+    /// Fx33 - Store BCD representation of Vx at I, I+1 and I+2
+    LdBcd { x: u8 },
+    /// Fx55 - Store registers V0 through Vx in memory starting at I
+    StoreRegs { x: u8 },
+    /// Fx65 - Read registers V0 through Vx from memory starting at I
+    LoadRegs { x: u8 },
+    /// 00Cn - Scroll the display down by n pixels (SUPER-CHIP)
+    ScrollDown(u8),
+    /// 00FB - Scroll the display right by 4 pixels (SUPER-CHIP)
+    ScrollRight,
+    /// 00FC - Scroll the display left by 4 pixels (SUPER-CHIP)
+    ScrollLeft,
+    /// 00FD - Exit the interpreter (SUPER-CHIP)
+    Exit,
+    /// 00FE - Switch to the 64x32 low resolution mode (SUPER-CHIP)
+    Lores,
+    /// 00FF - Switch to the 128x64 high resolution mode (SUPER-CHIP)
+    Hires,
+    /// Fx30 - Set I = location of the big font sprite for digit Vx (SUPER-CHIP)
+    LdHiFont { x: u8 },
+    /// Fx75 - Store registers V0 through Vx in the RPL flags (SUPER-CHIP)
+    StoreFlags { x: u8 },
+    /// Fx85 - Read registers V0 through Vx from the RPL flags (SUPER-CHIP)
+    LoadFlags { x: u8 },
+    /// 00Dn - Scroll the display up by n pixels (XO-CHIP)
+    ScrollUp(u8),
+    /// F000 nnnn - Set I = nnnn, a four byte instruction (XO-CHIP)
+    LdILong(u16),
+    /// Fn01 - Select the bitplanes n to draw to (XO-CHIP)
+    Plane(u8),
+    /// 5xy2 - Store registers Vx through Vy in memory starting at I (XO-CHIP)
+    SaveRange { x: u8, y: u8 },
+    /// 5xy3 - Read registers Vx through Vy from memory starting at I (XO-CHIP)
+    LoadRange { x: u8, y: u8 },
+    /// F002 - Load 16 bytes from I into the audio pattern buffer (XO-CHIP)
+    LdAudio,
+    /// Fx3A - Set the audio pattern playback pitch = Vx (XO-CHIP)
+    LdPitch { x: u8 },
+}
+
+// Opcode of the XO-CHIP long I load. The address
+// is stored in the two bytes following the opcode
+pub const LONG_LDI_OPCODE: u16 = 0xF000;
+
+// Raised when an opcode does not map to any instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeError {
+    pub opcode: u16,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Illegal instruction {:#06X}", self.opcode)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl Instruction {
+    // Decodes a four byte instruction, made up of an opcode and the
+    // 16-bit word following it. Only F000 nnnn uses the second word,
+    // any other opcode is decoded as by Instruction::decode
+    pub fn decode_long(opcode: u16, next: u16) -> Result<Instruction, DecodeError> {
+        if opcode == LONG_LDI_OPCODE {
+            Ok(Instruction::LdILong(next))
+        } else {
+            Instruction::decode(opcode)
+        }
+    }
+
+    // Decodes a raw 16-bit opcode into an instruction
+    // F000 nnnn is four bytes long, and has to be decoded
+    // using Instruction::decode_long
+    pub fn decode(opcode: u16) -> Result<Instruction, DecodeError> {
+        // Functions that parse out operation arguments from opcode
+        let nnn = opcode & 0x0FFF;
+        let x = ((opcode & 0x0F00) >> 8) as u8;
+        let y = ((opcode & 0x00F0) >> 4) as u8;
+        let kk = (opcode & 0x00FF) as u8;
+        let n = (opcode & 0x000F) as u8;
+        let ill = Err(DecodeError { opcode });
+
+        let inst = match (opcode & 0xF000) >> 12 {
+            0x0 => match opcode {
+                0x00E0 => Instruction::Cls,
+                0x00EE => Instruction::Ret,
+                0x00C0..=0x00CF => Instruction::ScrollDown(n),
+                0x00D0..=0x00DF => Instruction::ScrollUp(n),
+                0x00FB => Instruction::ScrollRight,
+                0x00FC => Instruction::ScrollLeft,
+                0x00FD => Instruction::Exit,
+                0x00FE => Instruction::Lores,
+                0x00FF => Instruction::Hires,
+                _ => Instruction::Sys(nnn),
+            },
+            0x1 => Instruction::Jp(nnn),
+            0x2 => Instruction::Call(nnn),
+            0x3 => Instruction::SeByte { x, kk },
+            0x4 => Instruction::SneByte { x, kk },
+            0x5 => match n {
+                0x0 => Instruction::SeReg { x, y },
+                0x2 => Instruction::SaveRange { x, y },
+                0x3 => Instruction::LoadRange { x, y },
+                _ => return ill,
+            },
+            0x6 => Instruction::LdByte { x, kk },
+            0x7 => Instruction::AddByte { x, kk },
+            // General purpose register instructions
+            // for arithmetic and logical operations
+            0x8 => match n {
+                0x0 => Instruction::LdReg { x, y },
+                0x1 => Instruction::Or { x, y },
+                0x2 => Instruction::And { x, y },
+                0x3 => Instruction::Xor { x, y },
+                0x4 => Instruction::AddReg { x, y },
+                0x5 => Instruction::Sub { x, y },
+                0x6 => Instruction::Shr { x, y },
+                0x7 => Instruction::Subn { x, y },
+                0xE => Instruction::Shl { x, y },
+                _ => return ill,
+            },
+            0x9 => match n {
+                0x0 => Instruction::SneReg { x, y },
+                _ => return ill,
+            },
+            0xA => Instruction::LdI(nnn),
+            0xB => Instruction::JpV0(nnn),
+            0xC => Instruction::Rnd { x, kk },
+            0xD => Instruction::Drw { x, y, n },
+            0xE => match kk {
+                0x9E => Instruction::Skp { x },
+                0xA1 => Instruction::Sknp { x },
+                _ => return ill,
+            },
+            0xF => match kk {
+                0x01 if x <= 0x3 => Instruction::Plane(x),
+                0x02 if x == 0 => Instruction::LdAudio,
+                0x07 => Instruction::LdVxDt { x },
+                0x0A => Instruction::LdKey { x },
+                0x15 => Instruction::LdDtVx { x },
+                0x18 => Instruction::LdStVx { x },
+                0x1E => Instruction::AddI { x },
+                0x29 => Instruction::LdFont { x },
+                0x30 => Instruction::LdHiFont { x },
+                0x3A => Instruction::LdPitch { x },
+                0x33 => Instruction::LdBcd { x },
+                0x55 => Instruction::StoreRegs { x },
+                0x65 => Instruction::LoadRegs { x },
+                0x75 => Instruction::StoreFlags { x },
+                0x85 => Instruction::LoadFlags { x },
+                _ => return ill,
+            },
+            _ => return ill,
+        };
+        Ok(inst)
+    }
+
+    // Size of the instruction in bytes
+    pub fn size(&self) -> u16 {
+        match self {
+            Instruction::LdILong(..) => 4,
+            _ => 2,
+        }
+    }
+
+    // Encodes the instruction into the bytes stored in RAM
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.encode().to_be_bytes().to_vec();
+        if let Instruction::LdILong(nnnn) = self {
+            bytes.extend_from_slice(&nnnn.to_be_bytes());
+        }
+        bytes
+    }
+
+    // Encodes the instruction into its raw 16-bit opcode
+    // For F000 nnnn only the opcode is returned, without the address
+    pub fn encode(&self) -> u16 {
+        // Packs the nibbles of an opcode together
+        let xy = |op: u16, x: u8, y: u8, n: u8| {
+            (op << 12) | ((x as u16 & 0xF) << 8) | ((y as u16 & 0xF) << 4) | (n as u16 & 0xF)
+        };
+        let xkk = |op: u16, x: u8, kk: u8| (op << 12) | ((x as u16 & 0xF) << 8) | kk as u16;
+        let nnn = |op: u16, nnn: u16| (op << 12) | (nnn & 0x0FFF);
+        match *self {
+            Instruction::Sys(addr) => nnn(0x0, addr),
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::Jp(addr) => nnn(0x1, addr),
+            Instruction::Call(addr) => nnn(0x2, addr),
+            Instruction::SeByte { x, kk } => xkk(0x3, x, kk),
+            Instruction::SneByte { x, kk } => xkk(0x4, x, kk),
+            Instruction::SeReg { x, y } => xy(0x5, x, y, 0x0),
+            Instruction::LdByte { x, kk } => xkk(0x6, x, kk),
+            Instruction::AddByte { x, kk } => xkk(0x7, x, kk),
+            Instruction::LdReg { x, y } => xy(0x8, x, y, 0x0),
+            Instruction::Or { x, y } => xy(0x8, x, y, 0x1),
+            Instruction::And { x, y } => xy(0x8, x, y, 0x2),
+            Instruction::Xor { x, y } => xy(0x8, x, y, 0x3),
+            Instruction::AddReg { x, y } => xy(0x8, x, y, 0x4),
+            Instruction::Sub { x, y } => xy(0x8, x, y, 0x5),
+            Instruction::Shr { x, y } => xy(0x8, x, y, 0x6),
+            Instruction::Subn { x, y } => xy(0x8, x, y, 0x7),
+            Instruction::Shl { x, y } => xy(0x8, x, y, 0xE),
+            Instruction::SneReg { x, y } => xy(0x9, x, y, 0x0),
+            Instruction::LdI(addr) => nnn(0xA, addr),
+            Instruction::JpV0(addr) => nnn(0xB, addr),
+            Instruction::Rnd { x, kk } => xkk(0xC, x, kk),
+            Instruction::Drw { x, y, n } => xy(0xD, x, y, n),
+            Instruction::Skp { x } => xkk(0xE, x, 0x9E),
+            Instruction::Sknp { x } => xkk(0xE, x, 0xA1),
+            Instruction::LdVxDt { x } => xkk(0xF, x, 0x07),
+            Instruction::LdKey { x } => xkk(0xF, x, 0x0A),
+            Instruction::LdDtVx { x } => xkk(0xF, x, 0x15),
+            Instruction::LdStVx { x } => xkk(0xF, x, 0x18),
+            Instruction::AddI { x } => xkk(0xF, x, 0x1E),
+            Instruction::LdFont { x } => xkk(0xF, x, 0x29),
+            Instruction::LdBcd { x } => xkk(0xF, x, 0x33),
+            Instruction::StoreRegs { x } => xkk(0xF, x, 0x55),
+            Instruction::LoadRegs { x } => xkk(0xF, x, 0x65),
+            Instruction::ScrollDown(n) => 0x00C0 | (n as u16 & 0xF),
+            Instruction::ScrollUp(n) => 0x00D0 | (n as u16 & 0xF),
+            Instruction::ScrollRight => 0x00FB,
+            Instruction::ScrollLeft => 0x00FC,
+            Instruction::Exit => 0x00FD,
+            Instruction::Lores => 0x00FE,
+            Instruction::Hires => 0x00FF,
+            Instruction::LdHiFont { x } => xkk(0xF, x, 0x30),
+            Instruction::StoreFlags { x } => xkk(0xF, x, 0x75),
+            Instruction::LoadFlags { x } => xkk(0xF, x, 0x85),
+            Instruction::LdILong(..) => LONG_LDI_OPCODE,
+            Instruction::Plane(n) => xkk(0xF, n, 0x01),
+            Instruction::SaveRange { x, y } => xy(0x5, x, y, 0x2),
+            Instruction::LoadRange { x, y } => xy(0x5, x, y, 0x3),
+            Instruction::LdAudio => 0xF002,
+            Instruction::LdPitch { x } => xkk(0xF, x, 0x3A),
+        }
+    }
+}
+
+// Formats the instruction as its assembly mnemonic,
+// e.g. `LD V1, 0x0A`, `DRW V0, V1, 5` or `LD B, V3`
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Sys(nnn) => write!(f, "SYS {:#05X}", nnn),
+            Instruction::Cls => write!(f, "CLS"),
+            Instruction::Ret => write!(f, "RET"),
+            Instruction::Jp(nnn) => write!(f, "JP {:#05X}", nnn),
+            Instruction::Call(nnn) => write!(f, "CALL {:#05X}", nnn),
+            Instruction::SeByte { x, kk } => write!(f, "SE V{:X}, {:#04X}", x, kk),
+            Instruction::SneByte { x, kk } => write!(f, "SNE V{:X}, {:#04X}", x, kk),
+            Instruction::SeReg { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::LdByte { x, kk } => write!(f, "LD V{:X}, {:#04X}", x, kk),
+            Instruction::AddByte { x, kk } => write!(f, "ADD V{:X}, {:#04X}", x, kk),
+            Instruction::LdReg { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::AddReg { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::Sub { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::Shr { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::Subn { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::Shl { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SneReg { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LdI(nnn) => write!(f, "LD I, {:#05X}", nnn),
+            Instruction::JpV0(nnn) => write!(f, "JP V0, {:#05X}", nnn),
+            Instruction::Rnd { x, kk } => write!(f, "RND V{:X}, {:#04X}", x, kk),
+            Instruction::Drw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::Skp { x } => write!(f, "SKP V{:X}", x),
+            Instruction::Sknp { x } => write!(f, "SKNP V{:X}", x),
+            Instruction::LdVxDt { x } => write!(f, "LD V{:X}, DT", x),
+            Instruction::LdKey { x } => write!(f, "LD V{:X}, K", x),
+            Instruction::LdDtVx { x } => write!(f, "LD DT, V{:X}", x),
+            Instruction::LdStVx { x } => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddI { x } => write!(f, "ADD I, V{:X}", x),
+            Instruction::LdFont { x } => write!(f, "LD F, V{:X}", x),
+            Instruction::LdBcd { x } => write!(f, "LD B, V{:X}", x),
+            Instruction::StoreRegs { x } => write!(f, "LD [I], V{:X}", x),
+            Instruction::LoadRegs { x } => write!(f, "LD V{:X}, [I]", x),
+            Instruction::ScrollDown(n) => write!(f, "SCD {}", n),
+            Instruction::ScrollUp(n) => write!(f, "SCU {}", n),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::Lores => write!(f, "LOW"),
+            Instruction::Hires => write!(f, "HIGH"),
+            Instruction::LdHiFont { x } => write!(f, "LD HF, V{:X}", x),
+            Instruction::StoreFlags { x } => write!(f, "LD R, V{:X}", x),
+            Instruction::LoadFlags { x } => write!(f, "LD V{:X}, R", x),
+            Instruction::LdILong(nnnn) => write!(f, "LD I, LONG {:#06X}", nnnn),
+            Instruction::Plane(n) => write!(f, "PLANE {}", n),
+            Instruction::SaveRange { x, y } => write!(f, "SAVE V{:X}, V{:X}", x, y),
+            Instruction::LoadRange { x, y } => write!(f, "LOAD V{:X}, V{:X}", x, y),
+            Instruction::LdAudio => write!(f, "AUDIO"),
+            Instruction::LdPitch { x } => write!(f, "PITCH V{:X}", x),
+        }
+    }
+}
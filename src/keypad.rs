@@ -0,0 +1,73 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Implements the state of the 16-key CHIP-8 keypad.
+ */
+
+// Chip8 keyboard consists of 16 different keys,
+// ranging from 0 to F
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Keypad {
+    keys: [bool; 16],
+}
+
+impl Keypad {
+    pub fn new() -> Self {
+        Keypad { keys: [false; 16] }
+    }
+
+    // Marks the key as held down
+    pub fn press(&mut self, key: usize) {
+        self.keys[key] = true;
+    }
+
+    // Marks the key as released
+    pub fn release(&mut self, key: usize) {
+        self.keys[key] = false;
+    }
+
+    // Returns true if the key is currently held
+    pub fn is_pressed(&self, key: usize) -> bool {
+        self.keys[key]
+    }
+
+    // If any key is pressed Some with the key value is returned
+    // else None is retrurned
+    pub fn first_pressed(&self) -> Option<usize> {
+        self.keys.iter().position(|&x| x)
+    }
+
+    // Releases all keys
+    pub fn clear(&mut self) {
+        self.keys = [false; 16];
+    }
+
+    // Returns the state of all 16 keys
+    pub fn state(&self) -> [bool; 16] {
+        self.keys
+    }
+
+    // Overwrites the state of all 16 keys
+    pub fn set_state(&mut self, keys: [bool; 16]) {
+        self.keys = keys;
+    }
+
+    // Packs the state of the keys into a bitmask, with bit n set if key n is held
+    pub fn bits(&self) -> u16 {
+        self.keys
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (i, &held)| bits | ((held as u16) << i))
+    }
+
+    // Unpacks a bitmask created by bits
+    pub fn from_bits(bits: u16) -> Self {
+        let mut keypad = Keypad::new();
+        for i in 0..16 {
+            keypad.keys[i] = (bits >> i) & 0x1 == 1;
+        }
+        keypad
+    }
+}
@@ -0,0 +1,56 @@
+/*
+    project: CHIP-8 Emulator
+    author: Fredrik Reinholdsen
+    email: fredrik.reinholdsen@gmail.com
+    gitlab: https://gitlab.com/fredrik.reinholdsen
+
+    info:
+        The emulator core. Contains the CHIP-8 virtual CPU, its display
+        buffer, keypad and timers, without any windowing dependencies,
+        so that the interpreter can be embedded in other tools, tests
+        and headless services. The ggez/egui frontend lives in the
+        `chip8` binary, behind the `gui` feature.
+*/
+pub mod asm;
+pub mod audio;
+pub mod breakpoint;
+pub mod config;
+pub mod cpu;
+pub mod disasm;
+pub mod display;
+pub mod error;
+pub mod gdb;
+pub mod instruction;
+pub mod keypad;
+pub mod movie;
+pub mod palette;
+pub mod quirks;
+pub mod rewind;
+pub mod rng;
+pub mod sprites;
+pub mod state;
+pub mod timers;
+#[cfg(feature = "debug")]
+pub mod trace;
+
+pub use audio::{AudioOutput, Buzzer, BuzzerSettings, NullOutput, Tee, WavOutput, Waveform};
+pub use breakpoint::{BreakEvent, BreakKind, BreakReason, Breakpoint, Condition, Register};
+pub use config::Config;
+pub use cpu::{Cpu, CpuBuilder, StepOutcome};
+pub use display::{
+    Chip8Display, DISPLAY_HEIGHT, DISPLAY_WIDTH, HIRES_DISPLAY_HEIGHT, HIRES_DISPLAY_WIDTH,
+};
+pub use error::Chip8Error;
+pub use gdb::GdbStub;
+pub use instruction::{DecodeError, Instruction};
+pub use keypad::Keypad;
+pub use movie::{Movie, MovieError};
+pub use palette::{Palette, PalettePreset};
+pub use quirks::{Quirks, QuirksPreset};
+pub use rewind::Rewind;
+pub use rng::Chip8Rng;
+pub use sprites::{SpriteKind, SpriteSheet};
+pub use state::StateError;
+pub use timers::Timers;
+#[cfg(feature = "debug")]
+pub use trace::{TraceEntry, TraceError, TraceFilter, Tracer};
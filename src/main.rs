@@ -9,265 +9,289 @@
         It is essentially an interpreted programming language, designed
         mainly for games. Programs run on a CHIP-8 virtual machine.
 */
-pub mod cpu;
+#[cfg(feature = "gui")]
+mod debugger;
+#[cfg(feature = "gui")]
+mod gui;
+mod headless;
+#[cfg(feature = "gui")]
+mod memory_viewer;
+#[cfg(feature = "gui")]
+mod renderer;
+#[cfg(feature = "gui")]
+mod speaker;
+#[cfg(feature = "gui")]
+mod sprite_viewer;
 
-use cpu::Cpu;
-use ggez_egui::{EguiBackend, egui};
-use ggez::{
-    event, graphics,
-    graphics::DrawParam,
-    input::keyboard::{is_key_pressed, KeyCode, KeyMods},
-    input::mouse::MouseButton,
-    Context, GameResult,
-};
+#[cfg(feature = "debug")]
+use chip8::trace::{self, TraceFilter, Tracer};
+use chip8::{asm, disasm, Quirks, QuirksPreset};
+use std::{env, fs, process};
+#[cfg(feature = "debug")]
+use std::{fs::File, io::BufWriter};
 
-const FPS: usize = 60;
-const DEFAULT_CLOCK_SPEED: usize = 500;
-const ROM: &str = "roms/1dcell.ch8";
+#[cfg(feature = "gui")]
+const DEFAULT_ROM: &str = "roms/1dcell.ch8";
 
-const DISPLAY_WIDTH: usize = 64;
-const DISPLAY_HEIGHT: usize = 32;
-const SCREEN_SIZE: (f32, f32) = (800.0, 400.0);
-const PIXEL_SIZE: (f32, f32) = (
-    SCREEN_SIZE.0 / DISPLAY_WIDTH as f32,
-    SCREEN_SIZE.1 / DISPLAY_HEIGHT as f32,
-);
+const DEFAULT_CLOCK_SPEED: usize = 500;
+// Frames run in headless mode unless --frames is given, 10 seconds
+const DEFAULT_HEADLESS_FRAMES: usize = 600;
 
-// Keys from 0-F that are used to emulate the
-// 16-key chip-8 keyboard
-const KEYS: [KeyCode; 16] = [
-    KeyCode::X,
-    KeyCode::Key1,
-    KeyCode::Key2,
-    KeyCode::Key3,
-    KeyCode::Q,
-    KeyCode::W,
-    KeyCode::E,
-    KeyCode::A,
-    KeyCode::S,
-    KeyCode::D,
-    KeyCode::Z,
-    KeyCode::C,
-    KeyCode::Key4,
-    KeyCode::R,
-    KeyCode::F,
-    KeyCode::V,
-];
+// Default history kept for rewinding in the frontend
+const DEFAULT_REWIND_SECONDS: usize = 10;
+const DEFAULT_REWIND_BUDGET: usize = 64 * 1024 * 1024;
 
-// Emulates the Chip8's attached 64x32 display
+const USAGE: &str = "Usage:
+    chip8 [run] [options] [rom]
+                           Runs a ROM in the emulator window
+    Options:
+        --clock <hz>       CPU clock speed, default 500
+        --quirks <preset>  Opcode quirks, one of vip, chip48, schip or xochip
+        --xo-chip          Enables XO-CHIP mode, with 64 kB of RAM
+        --rewind-seconds <n>
+                           Seconds of history kept for rewinding, default 10
+        --rewind-memory <mb>
+                           Memory used for rewinding in MB, default 64
+        --seed <n>         Seeds the random number generator, for reproducible runs
+        --record <movie>   Records the keypad input of the session to a movie file
+        --replay <movie>   Plays back a movie recorded with --record
+        --headless         Runs without a window, printing the final
+                           screen and registers to stdout
+        --frames <n>       Frames to run in headless mode, default 600
+        --input <script>   Keys to press in headless mode, one
+                           `<frame> press|release <keys>` per line
+        --png <file>       Writes the final screen to a PNG in headless mode
+        --wav <file>       Writes the buzzer audio to a WAV file in headless mode
+        --gdb <port>       Waits for GDB to connect on a local port,
+                           and runs the ROM under its control
+        --trace <file>     Writes every executed instruction to a file,
+                           in builds with the `debug` feature
+        --trace-binary     Writes the trace in the compact binary format
+        --trace-range <start>-<end>
+                           Only traces instructions in the address range
+        --trace-only <mnemonics>
+                           Only traces the listed instructions, e.g. DRW,CALL
+    chip8 disasm <rom>     Prints the disassembly of a ROM
+    chip8 asm <src> <rom>  Assembles a program into a ROM
+    chip8 trace <file>     Prints a binary trace as text";
 
-// CHip8 keyboard consists of 16 different keys,
-// ranging from 0 to F
-pub struct Chip8Display {
-    screen: [[bool; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
-}
-
-// Default implementation for display
-impl Default for Chip8Display {
-    fn default() -> Self {
-        Chip8Display {
-            screen: [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
-        }
+// Prints the disassembly of a ROM to stdout
+fn disasm(args: &[String]) -> Result<(), String> {
+    let path = match args {
+        [path] => path,
+        _ => return Err(USAGE.to_string()),
+    };
+    let rom = fs::read(path).map_err(|e| format!("Failed to read ROM {}: {}", path, e))?;
+    for line in disasm::disassemble(&rom) {
+        println!("{}", line);
     }
+    Ok(())
+}
 
+// Assembles a source file into a ROM
+fn asm(args: &[String]) -> Result<(), String> {
+    let (src, out) = match args {
+        [src, out] => (src, out),
+        _ => return Err(USAGE.to_string()),
+    };
+    let source =
+        fs::read_to_string(src).map_err(|e| format!("Failed to read source {}: {}", src, e))?;
+    let rom = asm::assemble(&source).map_err(|e| format!("{}:{}", src, e))?;
+    fs::write(out, rom).map_err(|e| format!("Failed to write ROM {}: {}", out, e))
 }
 
-impl Chip8Display {
-    // Clears the screen
-    pub fn new() -> Self {
-        Chip8Display {
-            screen: [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
-        }
+// Prints a binary trace written with --trace-binary as text
+#[cfg(feature = "debug")]
+fn print_trace(args: &[String]) -> Result<(), String> {
+    let path = match args {
+        [path] => path,
+        _ => return Err(USAGE.to_string()),
+    };
+    let data = fs::read(path).map_err(|e| format!("Failed to read trace {}: {}", path, e))?;
+    for entry in trace::read_binary(&data).map_err(|e| format!("{}: {}", path, e))? {
+        println!("{}", entry);
     }
+    Ok(())
+}
 
-    // Clears the screen
-    pub fn clear(&mut self) {
-        self.screen = [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
-    }
+// Parses the numeric value of a command line option
+fn parse_number<T: std::str::FromStr>(arg: Option<&String>) -> Result<T, String> {
+    let arg = arg.ok_or(USAGE)?;
+    arg.parse().map_err(|_| format!("Invalid number {}", arg))
+}
 
-    // ggez draw method for drawing the screen to the canvas
-    pub fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        // Clears the terminal before printing the display
-        (0..DISPLAY_HEIGHT).into_iter().for_each(|row| {
-            (0..DISPLAY_WIDTH).into_iter().for_each(|col| {
-                if self.screen[row][col] {
-                    let x = PIXEL_SIZE.1 * col as f32;
-                    let y = PIXEL_SIZE.0 * row as f32;
-                    let rect = graphics::Mesh::new_rectangle(
-                        ctx,
-                        graphics::DrawMode::fill(),
-                        [x, y, PIXEL_SIZE.0, PIXEL_SIZE.1].into(),
-                        [1.0, 1.0, 1.0, 1.0].into(),
-                    )
-                    .expect("Failed to create pixel mesh!");
-                    graphics::draw(ctx, &rect, DrawParam::new()).expect("Failed to draw display!");
-                }
-            });
-        });
-        Ok(())
-    }
+// Parses an address, in hex with or without a 0x prefix
+#[cfg(feature = "debug")]
+fn parse_address(arg: &str) -> Result<u16, String> {
+    let digits = arg.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("Invalid address {}", arg))
 }
 
-struct GameState {
-    egui_backend: EguiBackend,
-    cpu: cpu::Cpu,
-    // Number of CPU cycles/ticks executed
-    cycles: u128,
-    // Step through CPU ticks, one a the time
-    show_menu: bool,
+// Options of the execution trace
+#[cfg(feature = "debug")]
+#[derive(Default)]
+struct TraceOptions {
+    path: Option<String>,
+    binary: bool,
+    filter: TraceFilter,
 }
 
-impl GameState {
-    fn new() -> Self {
-        let mut cpu = Cpu::new(DEFAULT_CLOCK_SPEED);
-        match cpu.load_rom(ROM) {
-            Ok(..) => {}
-            Err(e) => panic!("Failed to load ROM!\n{}", e),
-        }
-        GameState {
-            egui_backend: EguiBackend::default(),
-            cpu,
-            cycles: 0,
-            show_menu: false,
-        }
+#[cfg(feature = "debug")]
+impl TraceOptions {
+    // Opens the trace file given with --trace, if any
+    fn open(&self) -> Result<Option<Tracer>, String> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let error = |e: std::io::Error| format!("Failed to write trace {}: {}", path, e);
+        let out = BufWriter::new(File::create(path).map_err(error)?);
+        let tracer = if self.binary {
+            Tracer::binary(out).map_err(error)?
+        } else {
+            Tracer::text(out)
+        };
+        Ok(Some(tracer.with_filter(self.filter.clone())))
     }
+}
 
-    // Draws the egui window
-    fn draw_egui(&mut self, ctx: &mut Context) -> GameResult {
-        let egui_ctx = self.egui_backend.ctx();
-            egui::Window::new("Options").open(&mut self.show_menu).show(&egui_ctx, |ui| {
-                ui.horizontal(|ui| {
-                    if ui.button("Pause").clicked() {
-                        self.cpu.set_hold_mode(true);
-                    }
-                    if ui.button("Play").clicked() {
-                        self.cpu.set_hold_mode(false);
-                    }
-                    if ui.button("Restart").clicked() {
-                        self.cpu.reset();
-                        self.cpu.load_rom(ROM)
-                            .expect("Failed to load ROM!");
-                    }
-                });
-                ui.separator();
-                ui.label("CPU Clock Speed:");
-                // Slider that changes the clock speed of the emulation
-                // thus speeding up or slowing down the game
-                ui.add(egui::Slider::new(&mut self.cpu.clock_speed, 50..=2000));
-                if ui.button("Quit").clicked() {
-                    ggez::event::quit(ctx)
-                }
-            });
-            Ok(())
-    }
+// Options of the run command
+struct RunOptions {
+    rom: Option<String>,
+    quirks: Quirks,
+    xo_chip: bool,
+    rewind_seconds: usize,
+    rewind_budget: usize,
+    seed: Option<u64>,
+    record: Option<String>,
+    replay: Option<String>,
+    clock_speed: usize,
+    headless: bool,
+    frames: usize,
+    input: Option<String>,
+    png: Option<String>,
+    wav: Option<String>,
+    gdb: Option<u16>,
+    #[cfg(feature = "debug")]
+    trace: TraceOptions,
 }
 
-// Implementations of the required ggez methods
-impl event::EventHandler<ggez::GameError> for GameState {
-    // Updates the state by ticking the CPU,
-    // fetching the next, 
-    // and instruction and executing that instruction
-    fn update(&mut self, ctx: &mut Context) -> GameResult {
-        while ggez::timer::check_update_time(ctx, self.cpu.clock_speed as u32) {
-            self.cpu.tick();
-            self.cycles += 1;
-            let cycles_per_frame = ((1.0 / FPS as f64) / (1.0 / self.cpu.clock_speed as f64)).round() as u128;
-            if self.cycles % cycles_per_frame == 0 {
-                self.draw(ctx)?;
-                self.draw_egui(ctx)?;
+fn parse_run_options(args: &[String]) -> Result<RunOptions, String> {
+    let mut options = RunOptions {
+        rom: None,
+        quirks: Quirks::default(),
+        xo_chip: false,
+        rewind_seconds: DEFAULT_REWIND_SECONDS,
+        rewind_budget: DEFAULT_REWIND_BUDGET,
+        seed: None,
+        record: None,
+        replay: None,
+        clock_speed: DEFAULT_CLOCK_SPEED,
+        headless: false,
+        frames: DEFAULT_HEADLESS_FRAMES,
+        input: None,
+        png: None,
+        wav: None,
+        gdb: None,
+        #[cfg(feature = "debug")]
+        trace: TraceOptions::default(),
+    };
+    let mut quirks_set = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--quirks" => {
+                let name = args.next().ok_or(USAGE)?;
+                options.quirks = QuirksPreset::from_arg(name)
+                    .ok_or(format!("Unknown quirks preset {}", name))?
+                    .quirks();
+                quirks_set = true;
             }
-        }
-        ctx.timer_context.tick();
-        Ok(())
-    }
-
-    /// draw is where we should actually render the game's current state.
-    fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        // First we create a canvas that renders to the frame, and clear it to a (sort of) green color
-        graphics::clear(ctx, [0.1, 0.1, 0.15, 1.0].into());
-        self.cpu.display.draw(ctx)?;
-        graphics::draw(ctx, &self.egui_backend, graphics::DrawParam::default())?;
-        //self.cpu.display.draw(&mut canvas);
-        graphics::present(ctx)?;
-        ggez::timer::yield_now();
-        Ok(())
-    }
-
-    /// key_down_event gets fired when a key gets pressed.
-    fn key_down_event(
-        &mut self,
-        _ctx: &mut Context,
-        keycode: KeyCode,
-        keymods: KeyMods,
-        _repeat: bool,
-    ) {
-        self.egui_backend.input.key_down_event(keycode, keymods);
-        match keycode {
-            // Toggles the menu
-            KeyCode::Return => {
-                self.show_menu = !self.show_menu;
+            "--xo-chip" => options.xo_chip = true,
+            "--rewind-seconds" => options.rewind_seconds = parse_number(args.next())?,
+            "--rewind-memory" => {
+                let megabytes: usize = parse_number(args.next())?;
+                options.rewind_budget = megabytes
+                    .checked_mul(1024 * 1024)
+                    .ok_or(format!("Invalid number {}", megabytes))?
             }
-            _ => {
-                // Lets the CPU know that a key is pressed
-                for (i, key) in KEYS.iter().enumerate() {
-                    if key == &keycode {
-                        self.cpu.pressed_keys[i] = true;
-                        return;
-                    }
-                }
+            "--seed" => options.seed = Some(parse_number(args.next())?),
+            "--record" => options.record = Some(args.next().ok_or(USAGE)?.clone()),
+            "--replay" => options.replay = Some(args.next().ok_or(USAGE)?.clone()),
+            "--clock" => options.clock_speed = parse_number(args.next())?,
+            "--headless" => options.headless = true,
+            "--frames" => options.frames = parse_number(args.next())?,
+            "--input" => options.input = Some(args.next().ok_or(USAGE)?.clone()),
+            "--png" => options.png = Some(args.next().ok_or(USAGE)?.clone()),
+            "--wav" => options.wav = Some(args.next().ok_or(USAGE)?.clone()),
+            "--gdb" => options.gdb = Some(parse_number(args.next())?),
+            #[cfg(feature = "debug")]
+            "--trace" => options.trace.path = Some(args.next().ok_or(USAGE)?.clone()),
+            #[cfg(feature = "debug")]
+            "--trace-binary" => options.trace.binary = true,
+            #[cfg(feature = "debug")]
+            "--trace-range" => {
+                let range = args.next().ok_or(USAGE)?;
+                let (start, end) = range.split_once('-').ok_or(USAGE)?;
+                options.trace.filter.range = Some((parse_address(start)?, parse_address(end)?));
             }
-        }
-    }
-
-    fn key_up_event(
-        &mut self,
-        _ctx: &mut Context,
-        keycode: KeyCode,
-        _keymods: KeyMods,
-    ) {
-        for (i, key) in KEYS.iter().enumerate() {
-            if key == &keycode {
-                self.cpu.pressed_keys[i] = false;
-                return;
+            #[cfg(feature = "debug")]
+            "--trace-only" => {
+                let mnemonics = args.next().ok_or(USAGE)?;
+                options.trace.filter.mnemonics = mnemonics.split(',').map(String::from).collect();
             }
+            _ if arg.starts_with("--") || options.rom.is_some() => return Err(USAGE.to_string()),
+            _ => options.rom = Some(arg.clone()),
         }
     }
-
-    // Input methods required for the egui GUI elements
-    fn resize_event(&mut self, ctx: &mut ggez::Context, width: f32, height: f32) {	
-		self.egui_backend.input.resize_event(width, height);
-		let rect = ggez::graphics::Rect::new(0.0, 0.0, width, height);
-		ggez::graphics::set_screen_coordinates(ctx, rect).unwrap();
+    // XO-CHIP programs expect the Octo quirks unless told otherwise
+    if options.xo_chip && !quirks_set {
+        options.quirks = QuirksPreset::XoChip.quirks();
     }
+    Ok(options)
+}
 
-    fn mouse_button_up_event(&mut self, _ctx: &mut ggez::Context, button: ggez::event::MouseButton, _x: f32, _y: f32) {
-        self.egui_backend.input.mouse_button_up_event(button);
+// Runs a ROM in the ggez/egui frontend, without a window, or under GDB
+#[cfg(feature = "gui")]
+fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_run_options(args)?;
+    if let Some(port) = options.gdb {
+        return headless::serve_gdb(&options, port);
     }
-
-    fn mouse_button_down_event(&mut self, _ctx: &mut ggez::Context, button: ggez::event::MouseButton, _x: f32, _y: f32) {
-        self.egui_backend.input.mouse_button_down_event(button);
-      }
-
-    fn mouse_wheel_event(&mut self, _ctx: &mut ggez::Context, x: f32, y: f32) {
-        self.egui_backend.input.mouse_wheel_event(x, y);
+    if options.headless {
+        return headless::run(&options);
     }
+    gui::run(&options).map_err(|e| e.to_string())
+}
 
-    fn mouse_motion_event(&mut self, _ctx: &mut ggez::Context, x: f32, y: f32, _dx: f32, _dy: f32) {
-        self.egui_backend.input.mouse_motion_event(x, y);
+#[cfg(not(feature = "gui"))]
+fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_run_options(args)?;
+    if let Some(port) = options.gdb {
+        return headless::serve_gdb(&options, port);
     }
-
+    if options.headless {
+        return headless::run(&options);
+    }
+    Err("chip8 was built without the `gui` feature, rebuild with `--features gui`".to_string())
 }
 
-fn main() -> GameResult {
-    let (ctx, events_loop) = ggez::ContextBuilder::new("chip8", "Fredrik Reinholdsen")
-        .window_setup(ggez::conf::WindowSetup::default().title("CHIP-8 Emulator"))
-        .window_mode(
-            ggez::conf::WindowMode::default()
-                .dimensions(SCREEN_SIZE.0 as f32, SCREEN_SIZE.1 as f32),
-        )
-        .build()?;
-
-    // Initialize game state struct and start running game
-    let state = GameState::new();
-    event::run(ctx, events_loop, state)
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(|s| s.as_str()) {
+        Some("disasm") => disasm(&args[1..]),
+        Some("asm") => asm(&args[1..]),
+        #[cfg(feature = "debug")]
+        Some("trace") => print_trace(&args[1..]),
+        Some("run") => run(&args[1..]),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => run(&args),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,282 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Memory viewer window of the frontend. Shows RAM as a
+ *                  hex dump with PC, the bytes pointed to by I and the
+ *                  stack return addresses highlighted, and lets the user
+ *                  search for byte patterns and edit bytes while paused.
+ */
+use chip8::cpu::PROGRAM_START;
+use chip8::instruction::LONG_LDI_OPCODE;
+use chip8::Cpu;
+use ggez_egui::egui::{self, Color32, RichText};
+
+// Bytes shown on each row
+const COLUMNS: usize = 16;
+// End of the fonts, the regular font at 0x000-0x04F
+// followed by the SUPER-CHIP big font at 0x050-0x0EF
+const FONT_END: usize = 0xF0;
+// Bytes highlighted from I onwards, the largest sprite DRW reads
+const I_REGION: usize = 32;
+
+const PC_COLOUR: Color32 = Color32::from_rgb(0x80, 0x60, 0x00);
+const I_COLOUR: Color32 = Color32::from_rgb(0x20, 0x60, 0x20);
+const STACK_COLOUR: Color32 = Color32::from_rgb(0x30, 0x40, 0x90);
+const MATCH_COLOUR: Color32 = Color32::from_rgb(0x90, 0x30, 0x30);
+const FONT_COLOUR: Color32 = Color32::from_rgb(0x70, 0xA0, 0xD0);
+const RESERVED_COLOUR: Color32 = Color32::from_gray(0x70);
+
+// Parses a search pattern of hex bytes, with or without spaces, e.g. `A2 1E` or `a21e`
+fn parse_pattern(pattern: &str) -> Option<Vec<u8>> {
+    let digits: String = pattern.split_whitespace().collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|n| u8::from_str_radix(&digits[n..n + 2], 16).ok())
+        .collect()
+}
+
+// Parses a hex address, with or without a 0x prefix
+fn parse_address(addr: &str) -> Option<usize> {
+    let addr = addr.trim();
+    let digits = addr
+        .strip_prefix("0x")
+        .or_else(|| addr.strip_prefix("0X"))
+        .unwrap_or(addr);
+    usize::from_str_radix(digits, 16).ok()
+}
+
+#[derive(Default)]
+pub struct MemoryViewer {
+    pub open: bool,
+    /// Address of the selected byte, which can be edited
+    selected: Option<usize>,
+    /// New value of the selected byte, as typed by the user
+    value: String,
+    /// Address typed into the go to field
+    goto: String,
+    /// Byte pattern typed into the search field
+    pattern: String,
+    /// Addresses where the search pattern was found, and its length
+    matches: Vec<usize>,
+    match_len: usize,
+    /// Index of the match last jumped to
+    current_match: usize,
+    /// Address to scroll to on the next frame
+    scroll_to: Option<usize>,
+    /// Message about the last search or edit
+    message: Option<String>,
+}
+
+impl MemoryViewer {
+    // Draws the memory viewer window. Edits are only possible while the
+    // emulation is paused, the address and new value of an edited byte
+    // are returned to the caller
+    pub fn show(&mut self, ctx: &egui::Context, cpu: &Cpu, paused: bool) -> Option<(u16, u8)> {
+        let mut edit = None;
+        let mut open = self.open;
+        egui::Window::new("Memory").open(&mut open).show(ctx, |ui| {
+            self.toolbar(ui, cpu);
+            edit = self.editor(ui, cpu, paused);
+            if let Some(message) = &self.message {
+                ui.label(message);
+            }
+            self.legend(ui);
+            ui.separator();
+            self.dump(ui, cpu);
+        });
+        self.open = open;
+        edit
+    }
+
+    fn toolbar(&mut self, ui: &mut egui::Ui, cpu: &Cpu) {
+        ui.horizontal(|ui| {
+            ui.label("Go to");
+            let field = ui.add(egui::TextEdit::singleline(&mut self.goto).desired_width(50.0));
+            let entered = field.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+            if ui.button("Go").clicked() || entered {
+                match parse_address(&self.goto).filter(|&addr| addr < cpu.memory().len()) {
+                    Some(addr) => self.select(addr),
+                    None => self.message = Some(format!("Invalid address {}", self.goto)),
+                }
+            }
+            if ui.button("PC").clicked() {
+                self.select(cpu.pc() as usize);
+            }
+            if ui.button("I").clicked() {
+                self.select(cpu.i() as usize % cpu.memory().len());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Find");
+            let field = ui.add(egui::TextEdit::singleline(&mut self.pattern).desired_width(100.0));
+            let entered = field.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+            if ui.button("Find").clicked() || entered {
+                self.search(cpu);
+            }
+            if !self.matches.is_empty() && ui.button("Next").clicked() {
+                self.current_match = (self.current_match + 1) % self.matches.len();
+                self.select(self.matches[self.current_match]);
+            }
+        });
+    }
+
+    // Finds every occurrence of the search pattern and selects the first
+    fn search(&mut self, cpu: &Cpu) {
+        self.matches.clear();
+        self.current_match = 0;
+        let pattern = match parse_pattern(&self.pattern) {
+            Some(pattern) => pattern,
+            None => {
+                self.message = Some(format!("Invalid byte pattern {}", self.pattern));
+                return;
+            }
+        };
+        self.match_len = pattern.len();
+        self.matches = cpu
+            .memory()
+            .windows(pattern.len())
+            .enumerate()
+            .filter(|(_, window)| *window == pattern.as_slice())
+            .map(|(addr, _)| addr)
+            .collect();
+        self.message = Some(format!("{} matches", self.matches.len()));
+        if let Some(&addr) = self.matches.first() {
+            self.select(addr);
+        }
+    }
+
+    fn select(&mut self, addr: usize) {
+        self.selected = Some(addr);
+        self.scroll_to = Some(addr);
+        self.value.clear();
+    }
+
+    // Field for a new value of the selected byte
+    fn editor(&mut self, ui: &mut egui::Ui, cpu: &Cpu, paused: bool) -> Option<(u16, u8)> {
+        let addr = self.selected?;
+        // The selection may be past the end of RAM after leaving XO-CHIP mode
+        let current = *cpu.memory().get(addr)?;
+        let mut edit = None;
+        ui.horizontal(|ui| {
+            ui.monospace(format!("{:#06X} = {:02X}", addr, current));
+            ui.add_enabled_ui(paused, |ui| {
+                ui.label("New value");
+                let field = ui.add(egui::TextEdit::singleline(&mut self.value).desired_width(30.0));
+                let entered = field.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+                if ui.button("Write").clicked() || entered {
+                    match u8::from_str_radix(self.value.trim(), 16) {
+                        Ok(value) => {
+                            edit = Some((addr as u16, value));
+                            self.message = None;
+                            // Moves on to the next byte, so that a sequence can be typed in
+                            if addr + 1 < cpu.memory().len() {
+                                self.selected = Some(addr + 1);
+                            }
+                            self.value.clear();
+                        }
+                        Err(_) => self.message = Some(format!("Invalid byte {}", self.value)),
+                    }
+                }
+            });
+        });
+        if !paused {
+            ui.label("Pause the emulation to edit memory");
+        }
+        edit
+    }
+
+    fn legend(&self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("PC").monospace().background_color(PC_COLOUR));
+            ui.label(RichText::new("I").monospace().background_color(I_COLOUR));
+            ui.label(
+                RichText::new("Stack")
+                    .monospace()
+                    .background_color(STACK_COLOUR),
+            );
+            ui.label(
+                RichText::new("Match")
+                    .monospace()
+                    .background_color(MATCH_COLOUR),
+            );
+            ui.label(RichText::new("Font").monospace().color(FONT_COLOUR));
+            ui.label(RichText::new("Reserved").monospace().color(RESERVED_COLOUR));
+            ui.label(RichText::new("Program").monospace());
+        });
+    }
+
+    // Highlight of the byte at addr, if any. PC takes precedence over
+    // the stack, the stack over search matches, and matches over I
+    fn highlight(&self, addr: usize, cpu: &Cpu) -> Option<Color32> {
+        let pc = cpu.pc() as usize;
+        let size = match cpu.memory().get(pc..pc + 2) {
+            Some(&[hi, lo]) if u16::from_be_bytes([hi, lo]) == LONG_LDI_OPCODE => 4,
+            _ => 2,
+        };
+        let i = cpu.i() as usize;
+        if (pc..pc + size).contains(&addr) {
+            Some(PC_COLOUR)
+        } else if cpu
+            .stack()
+            .iter()
+            .any(|&ret| (ret as usize..ret as usize + 2).contains(&addr))
+        {
+            Some(STACK_COLOUR)
+        } else if self
+            .matches
+            .iter()
+            .any(|&m| (m..m + self.match_len).contains(&addr))
+        {
+            Some(MATCH_COLOUR)
+        } else if (i..i + I_REGION).contains(&addr) {
+            Some(I_COLOUR)
+        } else {
+            None
+        }
+    }
+
+    // Draws the rows of the hex dump that are in view. Clicking a
+    // byte selects it for editing
+    fn dump(&mut self, ui: &mut egui::Ui, cpu: &Cpu) {
+        let text_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let row_height =
+            (text_height + 2.0 * ui.spacing().button_padding.y).max(ui.spacing().interact_size.y);
+        let rows = cpu.memory().len() / COLUMNS;
+        let mut scroll = egui::ScrollArea::vertical().auto_shrink([false, false]);
+        if let Some(addr) = self.scroll_to.take() {
+            let offset = (addr / COLUMNS) as f32 * (row_height + ui.spacing().item_spacing.y);
+            scroll = scroll.vertical_scroll_offset(offset);
+        }
+        scroll.show_rows(ui, row_height, rows, |ui, range| {
+            for row in range {
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 2.0;
+                    let start = row * COLUMNS;
+                    ui.monospace(format!("{:04X} ", start));
+                    for addr in start..start + COLUMNS {
+                        let mut text =
+                            RichText::new(format!("{:02X}", cpu.memory()[addr])).monospace();
+                        if addr < FONT_END {
+                            text = text.color(FONT_COLOUR);
+                        } else if addr < PROGRAM_START as usize {
+                            text = text.color(RESERVED_COLOUR);
+                        }
+                        if let Some(colour) = self.highlight(addr, cpu) {
+                            text = text.background_color(colour);
+                        }
+                        let selected = self.selected == Some(addr);
+                        if ui.selectable_label(selected, text).clicked() {
+                            self.selected = if selected { None } else { Some(addr) };
+                            self.value.clear();
+                        }
+                    }
+                });
+            }
+        });
+    }
+}
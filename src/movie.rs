@@ -0,0 +1,198 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Recordings of gameplay sessions. A movie holds the
+ *                  settings the session was started with and the keypad
+ *                  state of every frame, which is enough to replay it
+ *                  exactly, since the CPU is fully deterministic.
+ */
+use crate::cpu::Cpu;
+use crate::error::Chip8Error;
+use crate::keypad::Keypad;
+use crate::quirks::Quirks;
+use crate::state::{self, StateError, StateReader, StateWriter};
+use std::fmt;
+use std::fs;
+use std::io;
+
+// Magic number at the start of every movie file
+pub const MOVIE_MAGIC: [u8; 4] = *b"C8MV";
+// Version of the movie format, bumped whenever the layout changes
+pub const MOVIE_VERSION: u16 = 1;
+
+// Raised when a movie can not be loaded or played back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MovieError {
+    /// The data does not start with the movie magic number
+    BadMagic,
+    /// The movie was written by an incompatible version of the emulator
+    UnsupportedVersion { version: u16 },
+    /// The movie was recorded with a different ROM than the one given
+    RomMismatch { expected: u64, found: u64 },
+    /// The data ends before the whole movie has been read
+    Truncated,
+    /// A field holds a value that is not valid
+    Corrupt,
+    /// The ROM of the movie could not be loaded
+    Rom(Chip8Error),
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MovieError::BadMagic => write!(f, "Not a CHIP-8 movie"),
+            MovieError::UnsupportedVersion { version } => write!(
+                f,
+                "Movie version {} is not supported, expected version {}",
+                version, MOVIE_VERSION
+            ),
+            MovieError::RomMismatch { expected, found } => write!(
+                f,
+                "Movie was recorded with a different ROM (hash {:016X}, loaded ROM is {:016X})",
+                expected, found
+            ),
+            MovieError::Truncated => write!(f, "Movie is truncated"),
+            MovieError::Corrupt => write!(f, "Movie is corrupt"),
+            MovieError::Rom(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
+
+// The reader only fails on data that is cut short or invalid
+impl From<StateError> for MovieError {
+    fn from(e: StateError) -> Self {
+        match e {
+            StateError::Truncated => MovieError::Truncated,
+            _ => MovieError::Corrupt,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movie {
+    /// Hash of the ROM the movie was recorded with
+    pub rom_hash: u64,
+    /// Seed of the random number generator
+    pub seed: u64,
+    /// Clock speed of the CPU in Hz
+    pub clock_speed: usize,
+    /// Quirks of the CPU
+    pub quirks: Quirks,
+    /// Set if the movie was recorded in XO-CHIP mode
+    pub xo_chip: bool,
+    /// The keypad state at the start of every frame
+    pub frames: Vec<Keypad>,
+}
+
+impl Movie {
+    // Starts an empty movie with the settings of the CPU. The CPU
+    // should have just loaded the ROM, since movies always play
+    // back from the start of the program
+    pub fn new(cpu: &Cpu) -> Self {
+        Movie {
+            rom_hash: cpu.rom_hash(),
+            seed: cpu.seed(),
+            clock_speed: cpu.clock_speed,
+            quirks: cpu.quirks,
+            xo_chip: cpu.is_xo_chip(),
+            frames: vec![],
+        }
+    }
+
+    // Records the keypad state of the next frame
+    pub fn record(&mut self, keypad: &Keypad) {
+        self.frames.push(*keypad);
+    }
+
+    // Sets the CPU up like it was when the movie was recorded and loads
+    // the ROM. Fails if the ROM is not the one the movie was recorded with
+    pub fn start(&self, cpu: &mut Cpu, rom: &[u8]) -> Result<(), MovieError> {
+        let found = state::rom_hash(rom);
+        if found != self.rom_hash {
+            return Err(MovieError::RomMismatch {
+                expected: self.rom_hash,
+                found,
+            });
+        }
+        cpu.clock_speed = self.clock_speed;
+        cpu.quirks = self.quirks;
+        cpu.set_xo_chip(self.xo_chip);
+        cpu.set_seed(self.seed);
+        cpu.load_bytes(rom).map_err(MovieError::Rom)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = StateWriter::default();
+        w.bytes(&MOVIE_MAGIC);
+        w.u16(MOVIE_VERSION);
+        w.u64(self.rom_hash);
+        w.u64(self.seed);
+        w.u32(self.clock_speed as u32);
+        w.bool(self.quirks.shift_uses_vy);
+        w.bool(self.quirks.load_store_increments_i);
+        w.bool(self.quirks.jump_uses_vx);
+        w.bool(self.quirks.vf_reset);
+        w.bool(self.quirks.clip_sprites);
+        w.bool(self.quirks.display_wait);
+        w.bool(self.quirks.load_store_increments_by_x);
+        w.bool(self.xo_chip);
+        w.u32(self.frames.len() as u32);
+        for keypad in &self.frames {
+            w.u16(keypad.bits());
+        }
+        w.data
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, MovieError> {
+        let mut r = StateReader::new(data);
+        if r.array::<4>()? != MOVIE_MAGIC {
+            return Err(MovieError::BadMagic);
+        }
+        let version = r.u16()?;
+        if version != MOVIE_VERSION {
+            return Err(MovieError::UnsupportedVersion { version });
+        }
+        let rom_hash = r.u64()?;
+        let seed = r.u64()?;
+        let clock_speed = r.u32()? as usize;
+        let quirks = Quirks {
+            shift_uses_vy: r.bool()?,
+            load_store_increments_i: r.bool()?,
+            jump_uses_vx: r.bool()?,
+            vf_reset: r.bool()?,
+            clip_sprites: r.bool()?,
+            display_wait: r.bool()?,
+            load_store_increments_by_x: r.bool()?,
+        };
+        let xo_chip = r.bool()?;
+        let len = r.u32()? as usize;
+        let mut frames = Vec::with_capacity(len.min(data.len()));
+        for _ in 0..len {
+            frames.push(Keypad::from_bits(r.u16()?));
+        }
+        r.finish()?;
+        Ok(Movie {
+            rom_hash,
+            seed,
+            clock_speed,
+            quirks,
+            xo_chip,
+            frames,
+        })
+    }
+
+    // Writes the movie to a file
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    // Reads a movie from a file
+    pub fn load(path: &str) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        Movie::from_bytes(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
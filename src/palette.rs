@@ -0,0 +1,153 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Colours the display is drawn in. A palette has four
+ *                  colours, one for each XO-CHIP pixel value, so that
+ *                  programs drawing to both planes can be told apart.
+ */
+use std::fmt;
+use std::str::FromStr;
+
+// An RGB colour
+pub type Rgb = [u8; 3];
+
+// The colours of the four pixel values, indexed by the bitmask of the
+// planes a pixel is lit in. Index 0 is the background, and programs
+// that only draw to the first plane only use index 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [Rgb; 4],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        PalettePreset::Classic.palette()
+    }
+}
+
+// Built in palettes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PalettePreset {
+    /// White on dark blue, with orange and grey for the second plane
+    Classic,
+    /// Green phosphor monochrome monitor
+    Green,
+    /// Amber phosphor monochrome monitor
+    Amber,
+    /// The four shades of green of the original Game Boy
+    GameBoy,
+    /// Saturated colours on black, for low vision
+    HighContrast,
+}
+
+impl PalettePreset {
+    pub const ALL: [PalettePreset; 5] = [
+        PalettePreset::Classic,
+        PalettePreset::Green,
+        PalettePreset::Amber,
+        PalettePreset::GameBoy,
+        PalettePreset::HighContrast,
+    ];
+
+    // Human readable name of the preset
+    pub fn name(&self) -> &'static str {
+        match self {
+            PalettePreset::Classic => "Classic",
+            PalettePreset::Green => "Green phosphor",
+            PalettePreset::Amber => "Amber",
+            PalettePreset::GameBoy => "Game Boy",
+            PalettePreset::HighContrast => "High contrast",
+        }
+    }
+
+    // Looks up a preset by its short name, as used in the config file
+    pub fn from_arg(arg: &str) -> Option<PalettePreset> {
+        match arg.to_ascii_lowercase().as_str() {
+            "classic" => Some(PalettePreset::Classic),
+            "green" => Some(PalettePreset::Green),
+            "amber" => Some(PalettePreset::Amber),
+            "gameboy" => Some(PalettePreset::GameBoy),
+            "contrast" => Some(PalettePreset::HighContrast),
+            _ => None,
+        }
+    }
+
+    pub fn palette(&self) -> Palette {
+        let colors = match self {
+            PalettePreset::Classic => [0x1A1A26, 0xFFFFFF, 0xFF9933, 0x808080],
+            PalettePreset::Green => [0x0A140A, 0x33FF66, 0x1F9940, 0xB3FFC6],
+            PalettePreset::Amber => [0x140C00, 0xFFB000, 0x996A00, 0xFFE0A0],
+            PalettePreset::GameBoy => [0x9BBC0F, 0x0F380F, 0x306230, 0x8BAC0F],
+            PalettePreset::HighContrast => [0x000000, 0xFFFFFF, 0xFFFF00, 0x00FFFF],
+        };
+        Palette {
+            colors: colors.map(|rgb: u32| {
+                let [_, r, g, b] = rgb.to_be_bytes();
+                [r, g, b]
+            }),
+        }
+    }
+}
+
+impl Palette {
+    // Returns the preset these colours match, if any
+    pub fn preset(&self) -> Option<PalettePreset> {
+        PalettePreset::ALL
+            .iter()
+            .copied()
+            .find(|preset| preset.palette() == *self)
+    }
+
+    // Returns a colour as red, green, blue and alpha between 0 and 1
+    pub fn rgba(&self, index: usize) -> [f32; 4] {
+        let [r, g, b] = self.colors[index];
+        [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0]
+    }
+}
+
+// Parses a colour in the #RRGGBB hex notation, the # being optional
+pub fn parse_color(s: &str) -> Result<Rgb, String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    let valid = hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit());
+    let value = match u32::from_str_radix(hex, 16) {
+        Ok(value) if valid => value,
+        _ => return Err(format!("Invalid colour {}, expected #RRGGBB", s)),
+    };
+    let [_, r, g, b] = value.to_be_bytes();
+    Ok([r, g, b])
+}
+
+// Formats a colour in the #RRGGBB hex notation
+pub fn format_color(color: Rgb) -> String {
+    format!("#{:02X}{:02X}{:02X}", color[0], color[1], color[2])
+}
+
+// Formats as the four colours in hex, separated by spaces
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let colors: Vec<String> = self.colors.iter().map(|&c| format_color(c)).collect();
+        write!(f, "{}", colors.join(" "))
+    }
+}
+
+// Parses the short name of a preset, or four hex colours
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(preset) = PalettePreset::from_arg(s) {
+            return Ok(preset.palette());
+        }
+        let colors = s
+            .split_whitespace()
+            .map(parse_color)
+            .collect::<Result<Vec<Rgb>, String>>()?;
+        let colors = colors
+            .try_into()
+            .map_err(|_| format!("Expected a palette name or four colours, got {}", s))?;
+        Ok(Palette { colors })
+    }
+}
@@ -0,0 +1,127 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Configurable behaviour of the ambiguous CHIP-8
+ *                  opcodes, which differ between interpreters.
+ */
+
+// Each flag enables the behaviour of the original interpreters for
+// one of the ambiguous opcodes. With all flags off the CPU behaves
+// like most modern interpreters
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8xy6/8xyE shift Vy and store the result in Vx,
+    /// instead of shifting Vx in place
+    pub shift_uses_vy: bool,
+    /// Fx55/Fx65 leave I incremented by x + 1
+    pub load_store_increments_i: bool,
+    /// With load_store_increments_i, I is incremented by x only,
+    /// one short of the last register, as on CHIP-48
+    pub load_store_increments_by_x: bool,
+    /// Bnnn jumps to nnn + Vx, where x is the highest nibble of nnn,
+    /// instead of nnn + V0
+    pub jump_uses_vx: bool,
+    /// 8xy1/8xy2/8xy3 reset Vf to 0
+    pub vf_reset: bool,
+    /// Sprites are clipped at the edges of the screen instead of
+    /// wrapping around to the other side
+    pub clip_sprites: bool,
+    /// Dxyn waits for the next 60 Hz display refresh before drawing
+    pub display_wait: bool,
+}
+
+// Named sets of quirks, matching well known interpreters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuirksPreset {
+    /// The original interpreter on the COSMAC VIP
+    CosmacVip,
+    /// CHIP-48 on the HP-48 calculators
+    Chip48,
+    /// SUPER-CHIP 1.1 on the HP-48 calculators
+    SuperChip,
+    /// XO-CHIP, as implemented by Octo
+    XoChip,
+}
+
+impl QuirksPreset {
+    pub const ALL: [QuirksPreset; 4] = [
+        QuirksPreset::CosmacVip,
+        QuirksPreset::Chip48,
+        QuirksPreset::SuperChip,
+        QuirksPreset::XoChip,
+    ];
+
+    // Human readable name of the preset
+    pub fn name(&self) -> &'static str {
+        match self {
+            QuirksPreset::CosmacVip => "COSMAC VIP",
+            QuirksPreset::Chip48 => "CHIP-48",
+            QuirksPreset::SuperChip => "SUPER-CHIP",
+            QuirksPreset::XoChip => "XO-CHIP",
+        }
+    }
+
+    // Looks up a preset by its short command line name
+    pub fn from_arg(arg: &str) -> Option<QuirksPreset> {
+        match arg.to_ascii_lowercase().as_str() {
+            "vip" | "cosmac" | "chip8" => Some(QuirksPreset::CosmacVip),
+            "chip48" => Some(QuirksPreset::Chip48),
+            "schip" | "superchip" => Some(QuirksPreset::SuperChip),
+            "xochip" => Some(QuirksPreset::XoChip),
+            _ => None,
+        }
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        match self {
+            QuirksPreset::CosmacVip => Quirks {
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                load_store_increments_by_x: false,
+                jump_uses_vx: false,
+                vf_reset: true,
+                clip_sprites: true,
+                display_wait: true,
+            },
+            QuirksPreset::Chip48 => Quirks {
+                shift_uses_vy: false,
+                load_store_increments_i: true,
+                load_store_increments_by_x: true,
+                jump_uses_vx: true,
+                vf_reset: false,
+                clip_sprites: true,
+                display_wait: false,
+            },
+            QuirksPreset::SuperChip => Quirks {
+                shift_uses_vy: false,
+                load_store_increments_i: false,
+                load_store_increments_by_x: false,
+                jump_uses_vx: true,
+                vf_reset: false,
+                clip_sprites: true,
+                display_wait: false,
+            },
+            QuirksPreset::XoChip => Quirks {
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                load_store_increments_by_x: false,
+                jump_uses_vx: false,
+                vf_reset: false,
+                clip_sprites: false,
+                display_wait: false,
+            },
+        }
+    }
+}
+
+impl Quirks {
+    // Returns the preset these quirks match, if any
+    pub fn preset(&self) -> Option<QuirksPreset> {
+        QuirksPreset::ALL
+            .iter()
+            .copied()
+            .find(|preset| preset.quirks() == *self)
+    }
+}
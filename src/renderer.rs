@@ -0,0 +1,179 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Draws the CHIP-8 display as a single texture, one texel
+ *                  per pixel, scaled up to the window with nearest neighbour
+ *                  filtering. The texture is only uploaded when the screen
+ *                  changes, so drawing takes the same time however many
+ *                  pixels are lit. Optional filters hide the flicker of
+ *                  sprites that are erased and redrawn, by keeping pixels
+ *                  lit on screen after the program turns them off. They
+ *                  only change what is drawn, never the emulated screen.
+ */
+use chip8::{Chip8Display, Palette};
+use ggez::graphics::{self, Color, DrawParam, FilterMode, Image};
+use ggez::{Context, GameResult};
+use std::collections::VecDeque;
+
+// Most frames that the blend filter combines
+pub const MAX_BLEND_FRAMES: usize = 8;
+
+// Anti-flicker filter applied to the drawn screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// Pixels are drawn exactly as emulated
+    Off,
+    /// Pixels that are turned off fade out, like the phosphor of a CRT
+    Phosphor,
+    /// Pixels lit in any of the last frames are drawn lit
+    Blend,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 3] = [Filter::Off, Filter::Phosphor, Filter::Blend];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Filter::Off => "Off",
+            Filter::Phosphor => "Phosphor",
+            Filter::Blend => "Blend frames",
+        }
+    }
+}
+
+pub struct Renderer {
+    pub palette: Palette,
+    pub filter: Filter,
+    /// Brightness a faded pixel keeps from one frame to the next,
+    /// between 0 and 1, for the phosphor filter
+    pub decay: f32,
+    /// Number of frames combined by the blend filter
+    pub blend_frames: usize,
+    /// Colour each pixel has faded to, for the phosphor filter
+    glow: Vec<[f32; 4]>,
+    /// Screens of the last frames, newest last, for the blend filter
+    history: VecDeque<Vec<u8>>,
+    /// Texture of the screen, None until the first frame is drawn
+    image: Option<Image>,
+    /// RGBA pixels the texture was made from
+    rgba: Vec<u8>,
+    /// Buffer the next frame is converted into, swapped with rgba on upload
+    scratch: Vec<u8>,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer {
+            palette: Palette::default(),
+            filter: Filter::Off,
+            decay: 0.6,
+            blend_frames: 2,
+            glow: vec![],
+            history: VecDeque::new(),
+            image: None,
+            rgba: vec![],
+            scratch: vec![],
+        }
+    }
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Feeds the screen of a frame that was just run to the filter.
+    // Called once per emulated frame, so that pixels fade at the
+    // same rate whatever the rate of drawing
+    pub fn frame(&mut self, display: &Chip8Display) {
+        let screen = display.screen();
+        match self.filter {
+            Filter::Off => {
+                self.glow.clear();
+                self.history.clear();
+            }
+            Filter::Phosphor => {
+                let background = self.palette.rgba(0);
+                if self.glow.len() != screen.len() {
+                    self.glow = vec![background; screen.len()];
+                }
+                for (glow, &color) in self.glow.iter_mut().zip(screen) {
+                    *glow = if color != 0 {
+                        self.palette.rgba(color as usize)
+                    } else {
+                        fade(*glow, background, self.decay)
+                    };
+                }
+            }
+            Filter::Blend => {
+                // The old frames are of no use after a change of resolution
+                if self
+                    .history
+                    .front()
+                    .is_some_and(|old| old.len() != screen.len())
+                {
+                    self.history.clear();
+                }
+                self.history.push_back(screen.to_vec());
+                while self.history.len() > self.blend_frames {
+                    self.history.pop_front();
+                }
+            }
+        }
+    }
+
+    // Returns the colour of the pixel with index n in the screen
+    // buffer, after filtering
+    fn pixel(&self, n: usize, color: u8) -> [f32; 4] {
+        match self.filter {
+            Filter::Phosphor if color == 0 => {
+                self.glow.get(n).copied().unwrap_or(self.palette.rgba(0))
+            }
+            Filter::Blend => {
+                let color = self
+                    .history
+                    .iter()
+                    .filter_map(|old| old.get(n))
+                    .fold(color, |color, old| color | old);
+                self.palette.rgba(color as usize)
+            }
+            _ => self.palette.rgba(color as usize),
+        }
+    }
+
+    // Draws the display stretched to fill a screen of the given size
+    pub fn draw(
+        &mut self,
+        ctx: &mut Context,
+        display: &Chip8Display,
+        size: (f32, f32),
+    ) -> GameResult {
+        let (width, height) = (display.width(), display.height());
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+        for (n, &color) in display.screen().iter().enumerate() {
+            let (r, g, b, a) = Color::from(self.pixel(n, color)).to_rgba();
+            scratch.extend_from_slice(&[r, g, b, a]);
+        }
+        self.scratch = scratch;
+        // The buffer sizes differ when the resolution changes
+        if self.image.is_none() || self.scratch != self.rgba {
+            let mut image = Image::from_rgba8(ctx, width as u16, height as u16, &self.scratch)?;
+            image.set_filter(FilterMode::Nearest);
+            self.image = Some(image);
+            std::mem::swap(&mut self.rgba, &mut self.scratch);
+        }
+        if let Some(image) = &self.image {
+            let scale = [size.0 / width as f32, size.1 / height as f32];
+            graphics::draw(ctx, image, DrawParam::new().scale(scale))?;
+        }
+        Ok(())
+    }
+}
+
+// Moves a colour a step towards the background colour
+fn fade(color: [f32; 4], background: [f32; 4], decay: f32) -> [f32; 4] {
+    std::array::from_fn(|c| background[c] + (color[c] - background[c]) * decay)
+}
@@ -0,0 +1,76 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Ring buffer of per-frame save states, used by the
+ *                  frontend to run the emulation backwards.
+ */
+use std::collections::VecDeque;
+
+// Holds the most recent frames of history, dropping the oldest
+// frame when either the depth or the memory budget is exceeded
+pub struct Rewind {
+    frames: VecDeque<Vec<u8>>,
+    /// Maximum number of frames kept
+    depth: usize,
+    /// Maximum number of bytes used by the frames
+    budget: usize,
+    /// Number of bytes currently used by the frames
+    used: usize,
+}
+
+impl Rewind {
+    // Creates an empty buffer, holding seconds of history at fps frames per second
+    pub fn new(seconds: usize, fps: usize, budget: usize) -> Self {
+        Rewind {
+            frames: VecDeque::new(),
+            depth: seconds * fps,
+            budget,
+            used: 0,
+        }
+    }
+
+    // Changes the number of frames kept, dropping the oldest ones
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        self.evict();
+    }
+
+    // Records the state of a frame
+    pub fn push(&mut self, state: Vec<u8>) {
+        self.used += state.len();
+        self.frames.push_back(state);
+        self.evict();
+    }
+
+    // Takes the state of the most recent frame
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        let state = self.frames.pop_back()?;
+        self.used -= state.len();
+        Some(state)
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.used = 0;
+    }
+
+    // Number of frames of history
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    fn evict(&mut self) {
+        while self.frames.len() > self.depth || self.used > self.budget {
+            match self.frames.pop_front() {
+                Some(state) => self.used -= state.len(),
+                None => break,
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Small seedable random number generator used by the
+ *                  CXKK instruction. Its whole state is a single u64,
+ *                  so it can be stored in save states and replays.
+ */
+use rand::{RngCore, SeedableRng};
+
+// SplitMix64 generator. Every seed, including 0, gives a full
+// period sequence, and the same seed always gives the same sequence
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chip8Rng {
+    state: u64,
+}
+
+impl Chip8Rng {
+    pub fn new(seed: u64) -> Self {
+        Chip8Rng { state: seed }
+    }
+
+    // Returns the internal state, which restores the
+    // generator to this point when passed to new
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+}
+
+impl RngCore for Chip8Rng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Chip8Rng {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Chip8Rng::new(u64::from_le_bytes(seed))
+    }
+
+    fn seed_from_u64(state: u64) -> Self {
+        Chip8Rng::new(state)
+    }
+}
@@ -0,0 +1,88 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Audio output of the frontend, playing the samples
+ *                  rendered by the buzzer on the default sound device.
+ */
+use chip8::AudioOutput;
+use rodio::{OutputStream, Source};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Most audio queued up before old samples are dropped, so that
+// the sound never lags far behind the game after a stall
+const MAX_LATENCY: Duration = Duration::from_millis(100);
+
+// Samples waiting to be played, shared with the audio thread
+type Queue = Arc<Mutex<VecDeque<f32>>>;
+
+// Source handed to the audio thread, playing silence whenever
+// the emulation has not rendered enough samples
+struct QueueSource {
+    queue: Queue,
+    sample_rate: u32,
+}
+
+impl Iterator for QueueSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        Some(self.queue.lock().unwrap().pop_front().unwrap_or(0.0))
+    }
+}
+
+impl Source for QueueSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+pub struct Speaker {
+    queue: Queue,
+    max_queued: usize,
+    // Playback stops when the stream is dropped
+    _stream: OutputStream,
+}
+
+impl Speaker {
+    // Opens the default sound device. Returns None if there is none,
+    // in which case the emulator runs silently
+    pub fn new(sample_rate: u32) -> Option<Self> {
+        let (stream, handle) = OutputStream::try_default().ok()?;
+        let queue = Queue::default();
+        let source = QueueSource {
+            queue: queue.clone(),
+            sample_rate,
+        };
+        handle.play_raw(source).ok()?;
+        Some(Speaker {
+            queue,
+            max_queued: (sample_rate as f64 * MAX_LATENCY.as_secs_f64()) as usize,
+            _stream: stream,
+        })
+    }
+}
+
+impl AudioOutput for Speaker {
+    fn write(&mut self, samples: &[f32]) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.extend(samples);
+        let excess = queue.len().saturating_sub(self.max_queued);
+        queue.drain(..excess);
+    }
+}
@@ -0,0 +1,168 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Sprite viewer window of the frontend. Draws a range of
+ *                  RAM as a live sheet of sprites, highlighting the bytes
+ *                  the last DRW read, and exports the sheet as a PNG.
+ */
+use chip8::cpu::PROGRAM_START;
+use chip8::{Cpu, SpriteKind, SpriteSheet};
+use ggez_egui::egui::{self, Color32, Rect, Sense};
+
+// Largest number of sprites shown at once
+const MAX_SPRITES: usize = 128;
+
+const BACKGROUND: Color32 = Color32::from_gray(0x18);
+const CELL_BACKGROUND: Color32 = Color32::from_gray(0x30);
+const LAST_DRW_BACKGROUND: Color32 = Color32::from_rgb(0x20, 0x60, 0x20);
+const PIXEL: Color32 = Color32::WHITE;
+
+pub struct SpriteViewer {
+    pub open: bool,
+    /// Address of the first sprite
+    start: usize,
+    /// Number of sprites shown
+    count: usize,
+    /// Height of CHIP-8 sprites in rows
+    height: usize,
+    /// Shows 16x16 SUPER-CHIP sprites instead of CHIP-8 sprites
+    super_chip: bool,
+    /// Moves the start to each sprite DRW reads
+    follow: bool,
+    /// Screen pixels per sprite pixel
+    scale: usize,
+    /// File the sheet is exported to
+    path: String,
+    /// Result of the last export
+    message: Option<String>,
+}
+
+impl Default for SpriteViewer {
+    fn default() -> Self {
+        SpriteViewer {
+            open: false,
+            start: PROGRAM_START as usize,
+            count: 32,
+            height: 8,
+            super_chip: false,
+            follow: false,
+            scale: 4,
+            path: "sprites.png".to_string(),
+            message: None,
+        }
+    }
+}
+
+impl SpriteViewer {
+    fn kind(&self) -> SpriteKind {
+        if self.super_chip {
+            SpriteKind::SuperChip
+        } else {
+            SpriteKind::Chip8 {
+                height: self.height,
+            }
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, cpu: &Cpu) {
+        let mut open = self.open;
+        egui::Window::new("Sprites")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if self.follow {
+                    if let Some(last) = cpu.last_sprite() {
+                        self.start = last.start;
+                    }
+                }
+                self.settings(ui, cpu);
+                let sheet = SpriteSheet::new(cpu.memory(), self.start, self.count, self.kind());
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.path);
+                    if ui.button("Export PNG").clicked() {
+                        self.message = Some(match sheet.write_png(&self.path) {
+                            Ok(()) => format!("Saved {}", self.path),
+                            Err(e) => format!("Failed to write {}: {}", self.path, e),
+                        });
+                    }
+                });
+                if let Some(message) = &self.message {
+                    ui.label(message);
+                }
+                ui.separator();
+                egui::ScrollArea::both().show(ui, |ui| self.sheet(ui, cpu, &sheet));
+            });
+        self.open = open;
+    }
+
+    fn settings(&mut self, ui: &mut egui::Ui, cpu: &Cpu) {
+        let last_addr = cpu.memory().len() - 1;
+        ui.horizontal(|ui| {
+            ui.label("Start");
+            ui.add(
+                egui::DragValue::new(&mut self.start)
+                    .clamp_range(0..=last_addr)
+                    .hexadecimal(4, false, true)
+                    .prefix("0x"),
+            );
+            if ui.button("I").clicked() {
+                self.start = cpu.i() as usize % cpu.memory().len();
+            }
+            ui.checkbox(&mut self.follow, "Follow DRW");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Sprites");
+            ui.add(egui::DragValue::new(&mut self.count).clamp_range(1..=MAX_SPRITES));
+            ui.add_enabled_ui(!self.super_chip, |ui| {
+                ui.label("Height");
+                ui.add(egui::DragValue::new(&mut self.height).clamp_range(1..=15));
+            });
+            ui.checkbox(&mut self.super_chip, "16x16");
+            ui.label("Zoom");
+            ui.add(egui::DragValue::new(&mut self.scale).clamp_range(1..=16));
+        });
+    }
+
+    // Paints the sheet. Rows of sprites read by the last DRW get a
+    // coloured background, and hovering a sprite shows its address
+    fn sheet(&self, ui: &mut egui::Ui, cpu: &Cpu, sheet: &SpriteSheet) {
+        let scale = self.scale as f32;
+        let size = egui::vec2(sheet.width as f32, sheet.height as f32) * scale;
+        let (response, painter) = ui.allocate_painter(size, Sense::hover());
+        let origin = response.rect.min;
+        let rect = |x: usize, y: usize, w: usize, h: usize| {
+            Rect::from_min_size(
+                origin + egui::vec2(x as f32, y as f32) * scale,
+                egui::vec2(w as f32, h as f32) * scale,
+            )
+        };
+        painter.rect_filled(response.rect, 0.0, BACKGROUND);
+
+        let kind = sheet.kind;
+        let bytes_per_row = kind.width() / 8;
+        let last = cpu.last_sprite().unwrap_or(0..0);
+        let mut hovered = None;
+        for sprite in &sheet.sprites {
+            let cell = rect(sprite.x, sprite.y, kind.width(), kind.height());
+            painter.rect_filled(cell, 0.0, CELL_BACKGROUND);
+            for row in 0..kind.height() {
+                for byte in 0..bytes_per_row {
+                    if last.contains(&(sprite.addr + row * bytes_per_row + byte)) {
+                        let strip = rect(sprite.x + byte * 8, sprite.y + row, 8, 1);
+                        painter.rect_filled(strip, 0.0, LAST_DRW_BACKGROUND);
+                    }
+                }
+            }
+            if response.hover_pos().is_some_and(|pos| cell.contains(pos)) {
+                hovered = Some(sprite.addr);
+            }
+        }
+        for (n, _) in sheet.pixels.iter().enumerate().filter(|(_, &lit)| lit) {
+            painter.rect_filled(rect(n % sheet.width, n / sheet.width, 1, 1), 0.0, PIXEL);
+        }
+        if let Some(addr) = hovered {
+            response.on_hover_text(format!("{:#06X}", addr));
+        }
+    }
+}
@@ -0,0 +1,117 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Renders arbitrary RAM as a sheet of CHIP-8 sprites,
+ *                  used to find the sprite data in a ROM.
+ */
+use std::fs::File;
+use std::io::{self, BufWriter};
+
+// Sprites on each row of the sheet
+pub const SHEET_COLUMNS: usize = 8;
+// Empty pixels between neighbouring sprites
+const SPACING: usize = 1;
+
+// How the bytes of a sprite are laid out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpriteKind {
+    /// 8 pixels wide sprites of the given height, one byte per row,
+    /// as drawn by Dxyn
+    Chip8 { height: usize },
+    /// 16x16 pixel sprites, two bytes per row, as drawn by Dxy0
+    SuperChip,
+}
+
+impl SpriteKind {
+    pub fn width(&self) -> usize {
+        match self {
+            SpriteKind::Chip8 { .. } => 8,
+            SpriteKind::SuperChip => 16,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        match *self {
+            SpriteKind::Chip8 { height } => height,
+            SpriteKind::SuperChip => 16,
+        }
+    }
+
+    // Returns the number of bytes in each sprite
+    pub fn size(&self) -> usize {
+        self.width() / 8 * self.height()
+    }
+}
+
+// A sprite in the sheet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SheetSprite {
+    /// Address of the first byte of the sprite
+    pub addr: usize,
+    /// Position of the top left pixel in the sheet
+    pub x: usize,
+    pub y: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpriteSheet {
+    pub kind: SpriteKind,
+    pub width: usize,
+    pub height: usize,
+    /// Lit pixels of the sheet, row by row
+    pub pixels: Vec<bool>,
+    pub sprites: Vec<SheetSprite>,
+}
+
+impl SpriteSheet {
+    // Renders count sprites starting at start, laid out SHEET_COLUMNS
+    // to a row. Sprites running past the end of memory are cut short
+    pub fn new(memory: &[u8], start: usize, count: usize, kind: SpriteKind) -> Self {
+        let (cell_width, cell_height) = (kind.width() + SPACING, kind.height() + SPACING);
+        let columns = count.clamp(1, SHEET_COLUMNS);
+        let rows = count.div_ceil(SHEET_COLUMNS).max(1);
+        let width = columns * cell_width - SPACING;
+        let height = rows * cell_height - SPACING;
+        let mut pixels = vec![false; width * height];
+        let mut sprites = Vec::with_capacity(count);
+        for n in 0..count {
+            let sprite = SheetSprite {
+                addr: start + n * kind.size(),
+                x: n % SHEET_COLUMNS * cell_width,
+                y: n / SHEET_COLUMNS * cell_height,
+            };
+            for row in 0..kind.height() {
+                for col in 0..kind.width() {
+                    let addr = sprite.addr + row * kind.width() / 8 + col / 8;
+                    let byte = memory.get(addr).copied().unwrap_or(0);
+                    if byte & (0x80 >> (col % 8)) != 0 {
+                        pixels[(sprite.y + row) * width + sprite.x + col] = true;
+                    }
+                }
+            }
+            sprites.push(sprite);
+        }
+        SpriteSheet {
+            kind,
+            width,
+            height,
+            pixels,
+            sprites,
+        }
+    }
+
+    // Writes the sheet as a black and white PNG, one image pixel per sprite pixel
+    pub fn write_png(&self, path: &str) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let pixels: Vec<u8> = self.pixels.iter().map(|&lit| lit as u8 * 0xFF).collect();
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&pixels))
+            .map_err(|e| io::Error::other(e.to_string()))
+    }
+}
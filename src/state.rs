@@ -0,0 +1,167 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Binary format of save states. A state starts with
+ *                  a magic number, a format version and the hash of
+ *                  the ROM it was saved from, followed by the CPU state.
+ */
+use std::fmt;
+
+// Magic number at the start of every save state
+pub const STATE_MAGIC: [u8; 4] = *b"C8ST";
+// Version of the save state format, bumped whenever the layout changes
+pub const STATE_VERSION: u16 = 2;
+
+// Raised when a save state can not be loaded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateError {
+    /// The data does not start with the save state magic number
+    BadMagic,
+    /// The state was written by an incompatible version of the emulator
+    UnsupportedVersion { version: u16 },
+    /// The state was saved from a different ROM than the one loaded
+    RomMismatch { expected: u64, found: u64 },
+    /// The data ends before the whole state has been read
+    Truncated,
+    /// A field holds a value that is not valid for the CPU
+    Corrupt,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::BadMagic => write!(f, "Not a CHIP-8 save state"),
+            StateError::UnsupportedVersion { version } => write!(
+                f,
+                "Save state version {} is not supported, expected version {}",
+                version, STATE_VERSION
+            ),
+            StateError::RomMismatch { expected, found } => write!(
+                f,
+                "Save state belongs to a different ROM (hash {:016X}, loaded ROM is {:016X})",
+                found, expected
+            ),
+            StateError::Truncated => write!(f, "Save state is truncated"),
+            StateError::Corrupt => write!(f, "Save state is corrupt"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+// 64-bit FNV-1a hash, used to tie save states to the ROM they belong to
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    rom.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+// Appends little endian fields to a save state
+#[derive(Default)]
+pub(crate) struct StateWriter {
+    pub data: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.data.push(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    // Writes a length prefixed byte buffer
+    pub fn buffer(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.bytes(bytes);
+    }
+}
+
+// Reads the fields written by StateWriter back, in the same order
+pub(crate) struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        StateReader { data }
+    }
+
+    pub fn bytes(&mut self, n: usize) -> Result<&'a [u8], StateError> {
+        if self.data.len() < n {
+            return Err(StateError::Truncated);
+        }
+        let (bytes, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    // Reads exactly N bytes into an array
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Corrupt),
+        }
+    }
+
+    pub fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    // Reads a length prefixed byte buffer
+    pub fn buffer(&mut self) -> Result<&'a [u8], StateError> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // Fails if there is data left after the state
+    pub fn finish(self) -> Result<(), StateError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(StateError::Corrupt)
+        }
+    }
+}
@@ -0,0 +1,52 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Implements the delay and sound timer registers.
+ */
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timers {
+    /// Delay timer register
+    /// Decremented by 1 at 60 Hz
+    pub(crate) dt: u8,
+    /// Sound timer register
+    /// Decremented by 1 at 60 Hz
+    pub(crate) st: u8,
+}
+
+impl Timers {
+    pub fn new() -> Self {
+        Timers { dt: 0x00, st: 0x00 }
+    }
+
+    // Returns the current value of the delay timer
+    pub fn delay(&self) -> u8 {
+        self.dt
+    }
+
+    // Returns the current value of the sound timer
+    pub fn sound(&self) -> u8 {
+        self.st
+    }
+
+    // The buzzer sounds as long as the sound timer is active
+    pub fn sound_active(&self) -> bool {
+        self.st > 0
+    }
+
+    // This function is run at a frequency of
+    // 60 Hz. A timer is active as long as the timer
+    // value is greater than 0
+    // while a timer is active it is decremented by 1
+    // at a rate of 60 Hz until it deactivates
+    pub fn update(&mut self) {
+        if self.dt > 0 {
+            self.dt -= 1;
+        }
+        if self.st > 0 {
+            self.st -= 1;
+        }
+    }
+}
@@ -0,0 +1,299 @@
+/*
+ *    Created     - 2022-06-27 10:12:41
+ *    Updated     - 2022-06-27 10:12:41
+ *    Author      - Fredrik Reinholdsen
+ *    Project     - CHIP-8 Emulator
+ *    Description - Instruction level execution traces. A tracer attached
+ *                  to the CPU records every executed instruction with
+ *                  its effects, as text, in a compact binary format, or
+ *                  in a ring buffer holding the most recent entries.
+ *                  Only built with the `debug` feature.
+ */
+use crate::instruction::{Instruction, LONG_LDI_OPCODE};
+use crate::state::{StateError, StateReader, StateWriter};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Write};
+
+// Magic number at the start of every binary trace
+pub const TRACE_MAGIC: [u8; 4] = *b"C8TR";
+// Version of the binary trace format, bumped whenever the layout changes
+pub const TRACE_VERSION: u16 = 1;
+
+// Raised when a binary trace can not be read back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceError {
+    /// The data does not start with the trace magic number
+    BadMagic,
+    /// The trace was written by an incompatible version of the emulator
+    UnsupportedVersion { version: u16 },
+    /// The data ends in the middle of an entry
+    Truncated,
+    /// An entry holds a value that is not valid
+    Corrupt,
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceError::BadMagic => write!(f, "Not a CHIP-8 binary trace"),
+            TraceError::UnsupportedVersion { version } => write!(
+                f,
+                "Trace version {} is not supported, expected version {}",
+                version, TRACE_VERSION
+            ),
+            TraceError::Truncated => write!(f, "Trace is truncated"),
+            TraceError::Corrupt => write!(f, "Trace is corrupt"),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+// The reader only fails on data that is cut short or invalid
+impl From<StateError> for TraceError {
+    fn from(e: StateError) -> Self {
+        match e {
+            StateError::Truncated => TraceError::Truncated,
+            _ => TraceError::Corrupt,
+        }
+    }
+}
+
+// One executed instruction and its effects
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// CPU cycle the instruction was executed in
+    pub cycle: u64,
+    /// Address of the instruction
+    pub pc: u16,
+    /// First word of the instruction
+    pub opcode: u16,
+    pub inst: Instruction,
+    /// The V registers changed by the instruction, as (x, old, new)
+    pub registers: Vec<(u8, u8, u8)>,
+    /// The I register after the instruction
+    pub i: u16,
+    /// The RAM written by the instruction, as (address, value)
+    pub writes: Vec<(u16, u8)>,
+}
+
+impl TraceEntry {
+    fn write_binary(&self, w: &mut StateWriter) {
+        w.u64(self.cycle);
+        w.u16(self.pc);
+        w.u16(self.opcode);
+        w.u16(self.i);
+        w.u8(self.registers.len() as u8);
+        for &(x, old, new) in &self.registers {
+            w.u8(x);
+            w.u8(old);
+            w.u8(new);
+        }
+        w.u16(self.writes.len() as u16);
+        for &(addr, value) in &self.writes {
+            w.u16(addr);
+            w.u8(value);
+        }
+    }
+
+    fn read_binary(r: &mut StateReader) -> Result<Self, TraceError> {
+        let cycle = r.u64()?;
+        let pc = r.u16()?;
+        let opcode = r.u16()?;
+        let i = r.u16()?;
+        // The address of the long I load is not stored, as it is
+        // the value of I after the instruction
+        let inst = if opcode == LONG_LDI_OPCODE {
+            Instruction::decode_long(opcode, i)
+        } else {
+            Instruction::decode(opcode)
+        };
+        let inst = inst.map_err(|_| TraceError::Corrupt)?;
+        let mut registers = vec![];
+        for _ in 0..r.u8()? {
+            registers.push((r.u8()?, r.u8()?, r.u8()?));
+        }
+        let mut writes = vec![];
+        for _ in 0..r.u16()? {
+            writes.push((r.u16()?, r.u8()?));
+        }
+        Ok(TraceEntry {
+            cycle,
+            pc,
+            opcode,
+            inst,
+            registers,
+            i,
+            writes,
+        })
+    }
+}
+
+// Formats an entry as one line, e.g.
+// `     412 0206 D015 DRW V0, V1, 5      VF=00->01 I=0300`
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:>8} {:04X} {:04X} {:<20}",
+            self.cycle,
+            self.pc,
+            self.opcode,
+            self.inst.to_string()
+        )?;
+        for (x, old, new) in &self.registers {
+            write!(f, " V{:X}={:02X}->{:02X}", x, old, new)?;
+        }
+        write!(f, " I={:04X}", self.i)?;
+        for (addr, value) in &self.writes {
+            write!(f, " [{:04X}]={:02X}", addr, value)?;
+        }
+        Ok(())
+    }
+}
+
+// Selects the instructions that are traced
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraceFilter {
+    /// Inclusive range of instruction addresses, all if None
+    pub range: Option<(u16, u16)>,
+    /// Mnemonics of the traced instructions, e.g. DRW, all if empty
+    pub mnemonics: Vec<String>,
+}
+
+impl TraceFilter {
+    pub fn matches(&self, pc: u16, inst: &Instruction) -> bool {
+        let in_range = self
+            .range
+            .is_none_or(|(start, end)| (start..=end).contains(&pc));
+        let text = inst.to_string();
+        let mnemonic = text.split_whitespace().next().unwrap_or("");
+        in_range
+            && (self.mnemonics.is_empty()
+                || self
+                    .mnemonics
+                    .iter()
+                    .any(|m| m.eq_ignore_ascii_case(mnemonic)))
+    }
+}
+
+// Where the traced instructions go
+pub enum TraceSink {
+    /// One line of text per instruction
+    Text(Box<dyn Write + Send>),
+    /// The binary format, which is less than half the size of text
+    Binary(Box<dyn Write + Send>),
+    /// Keeps the most recent instructions in memory
+    Ring {
+        entries: VecDeque<TraceEntry>,
+        capacity: usize,
+    },
+}
+
+pub struct Tracer {
+    pub filter: TraceFilter,
+    sink: TraceSink,
+    /// First error writing the trace. Tracing stops after an error
+    error: Option<io::Error>,
+}
+
+impl Tracer {
+    // Writes a text trace
+    pub fn text<W: Write + Send + 'static>(out: W) -> Self {
+        Tracer::new(TraceSink::Text(Box::new(out)))
+    }
+
+    // Writes a binary trace, starting with its header
+    pub fn binary<W: Write + Send + 'static>(mut out: W) -> io::Result<Self> {
+        let mut w = StateWriter::default();
+        w.bytes(&TRACE_MAGIC);
+        w.u16(TRACE_VERSION);
+        out.write_all(&w.data)?;
+        Ok(Tracer::new(TraceSink::Binary(Box::new(out))))
+    }
+
+    // Keeps the last capacity instructions in memory
+    pub fn ring(capacity: usize) -> Self {
+        Tracer::new(TraceSink::Ring {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        })
+    }
+
+    fn new(sink: TraceSink) -> Self {
+        Tracer {
+            filter: TraceFilter::default(),
+            sink,
+            error: None,
+        }
+    }
+
+    pub fn with_filter(mut self, filter: TraceFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    // Records an executed instruction, if the filter selects it
+    pub fn record(&mut self, entry: TraceEntry) {
+        if self.error.is_some() || !self.filter.matches(entry.pc, &entry.inst) {
+            return;
+        }
+        let result = match &mut self.sink {
+            TraceSink::Text(out) => writeln!(out, "{}", entry),
+            TraceSink::Binary(out) => {
+                let mut w = StateWriter::default();
+                entry.write_binary(&mut w);
+                out.write_all(&w.data)
+            }
+            TraceSink::Ring { entries, capacity } => {
+                if entries.len() == *capacity {
+                    entries.pop_front();
+                }
+                if *capacity > 0 {
+                    entries.push_back(entry);
+                }
+                Ok(())
+            }
+        };
+        self.error = result.err();
+    }
+
+    // Returns the instructions kept in the ring buffer, oldest first.
+    // Empty for tracers writing to a file
+    pub fn entries(&self) -> impl Iterator<Item = &TraceEntry> {
+        let entries = match &self.sink {
+            TraceSink::Ring { entries, .. } => Some(entries.iter()),
+            _ => None,
+        };
+        entries.into_iter().flatten()
+    }
+
+    // Flushes the trace, returning the first error writing it
+    pub fn finish(&mut self) -> io::Result<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        match &mut self.sink {
+            TraceSink::Text(out) | TraceSink::Binary(out) => out.flush(),
+            TraceSink::Ring { .. } => Ok(()),
+        }
+    }
+}
+
+// Reads back a binary trace written by Tracer::binary
+pub fn read_binary(data: &[u8]) -> Result<Vec<TraceEntry>, TraceError> {
+    let mut r = StateReader::new(data);
+    if r.array::<4>()? != TRACE_MAGIC {
+        return Err(TraceError::BadMagic);
+    }
+    let version = r.u16()?;
+    if version != TRACE_VERSION {
+        return Err(TraceError::UnsupportedVersion { version });
+    }
+    let mut entries = vec![];
+    while !r.is_empty() {
+        entries.push(TraceEntry::read_binary(&mut r)?);
+    }
+    Ok(entries)
+}
@@ -0,0 +1,108 @@
+use chip8::asm::assemble;
+use chip8::disasm::{disassemble, Line};
+use chip8::{Cpu, Instruction};
+
+#[test]
+fn every_instruction_round_trips() {
+    for opcode in 0..=0xFFFF_u16 {
+        if let Ok(inst) = Instruction::decode(opcode) {
+            let rom = assemble(&inst.to_string())
+                .unwrap_or_else(|e| panic!("{} failed to assemble: {}", inst, e));
+            assert_eq!(rom, opcode.to_be_bytes(), "{} did not round-trip", inst);
+        }
+    }
+}
+
+#[test]
+fn labels_constants_and_data() {
+    let source = "
+        ; Draws a sprite in the top left corner
+        ROW EQU 0
+        start:  LD I, sprite
+                LD V0, ROW
+                DRW V0, V0, 2
+        loop:   JP loop       ; Spin forever
+        sprite: DB \"#......#\", %01111110
+                DW 0x1234, start
+    ";
+    let rom = assemble(source).unwrap();
+    assert_eq!(
+        rom,
+        [0xA2, 0x08, 0x60, 0x00, 0xD0, 0x02, 0x12, 0x06, 0x81, 0x7E, 0x12, 0x34, 0x02, 0x00]
+    );
+
+    // The code decodes back into the same instructions,
+    // while the sprite is detected as data
+    let lines = disassemble(&rom);
+    let code: Vec<String> = lines
+        .iter()
+        .filter_map(|line| match line {
+            Line::Code { inst, .. } => Some(inst.to_string()),
+            Line::Data { .. } => None,
+        })
+        .collect();
+    assert_eq!(
+        code,
+        ["LD I, 0x208", "LD V0, 0x00", "DRW V0, V0, 2", "JP 0x206"]
+    );
+    assert!(matches!(
+        lines[4],
+        Line::Data {
+            addr: 0x208,
+            byte: 0x81
+        }
+    ));
+}
+
+#[test]
+fn assembled_program_runs() {
+    let source = "
+            LD I, sprite
+            LD V0, 0
+            DRW V0, V0, 1
+        end: JP end
+        sprite: DB \"#......#\"
+    ";
+    let mut cpu = Cpu::new(500);
+    cpu.load_bytes(&assemble(source).unwrap()).unwrap();
+    for _ in 0..4 {
+        cpu.tick().unwrap();
+    }
+    assert!(cpu.display.pixel(0, 0));
+    assert!(!cpu.display.pixel(1, 0));
+    assert!(cpu.display.pixel(7, 0));
+}
+
+#[test]
+fn errors_report_line() {
+    let err = assemble("CLS\nJP nowhere\n").unwrap_err();
+    assert_eq!(err.line, 2);
+    let err = assemble("LD V0, 0x100").unwrap_err();
+    assert_eq!(err.line, 1);
+    let err = assemble("a: CLS\na: RET").unwrap_err();
+    assert_eq!(err.line, 2);
+}
+
+#[test]
+fn xo_chip_program_draws_to_both_planes() {
+    let source = "
+            PLANE 3
+            LD V0, 0
+            SE V0, 0
+            LD I, LONG end
+            LD I, LONG sprite
+            DRW V0, V0, 1
+        end: JP end
+        sprite: DB \"##......\", \".#......\"
+    ";
+    let mut cpu = Cpu::new(500);
+    cpu.set_xo_chip(true);
+    cpu.load_bytes(&assemble(source).unwrap()).unwrap();
+    for _ in 0..5 {
+        cpu.tick().unwrap();
+    }
+    // The skip jumps over the whole four byte instruction
+    assert_eq!(cpu.display.color(0, 0), 0x1);
+    assert_eq!(cpu.display.color(1, 0), 0x3);
+    assert_eq!(cpu.display.color(2, 0), 0x0);
+}
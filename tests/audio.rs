@@ -0,0 +1,135 @@
+use chip8::asm::assemble;
+use chip8::audio::{WavOutput, DEFAULT_SAMPLE_RATE};
+use chip8::{Buzzer, BuzzerSettings, Cpu, Waveform};
+use std::env;
+
+// Sounds the buzzer for 6 frames, then spins
+fn beeping_cpu(xo_chip: bool) -> Cpu {
+    let rom = assemble(
+        "
+            LD V0, 6
+            LD ST, V0
+        loop:
+            JP loop
+    ",
+    )
+    .unwrap();
+    let mut cpu = Cpu::builder(600).seed(0).xo_chip(xo_chip).build();
+    cpu.load_bytes(&rom).unwrap();
+    cpu
+}
+
+// Runs a frame, returning the samples the buzzer rendered for it
+fn frame(cpu: &mut Cpu, buzzer: &mut Buzzer) -> Vec<f32> {
+    cpu.run_frame_with(|cpu| buzzer.cycle(cpu)).unwrap();
+    let mut samples = vec![];
+    buzzer.flush(&mut samples);
+    samples
+}
+
+#[test]
+fn buzzer_sounds_while_sound_timer_runs() {
+    let mut cpu = beeping_cpu(false);
+    let mut buzzer = Buzzer::new(BuzzerSettings::default(), DEFAULT_SAMPLE_RATE);
+    let frames: Vec<Vec<f32>> = (0..10).map(|_| frame(&mut cpu, &mut buzzer)).collect();
+    for samples in &frames {
+        assert_eq!(samples.len(), DEFAULT_SAMPLE_RATE as usize / 60);
+    }
+    assert!(frames[1].iter().any(|&s| s != 0.0));
+    assert!(frames[1].iter().all(|&s| s.abs() <= 0.25));
+    assert!(frames[9].iter().all(|&s| s == 0.0));
+}
+
+#[test]
+fn muted_buzzer_is_silent() {
+    let mut cpu = beeping_cpu(false);
+    let settings = BuzzerSettings {
+        muted: true,
+        ..BuzzerSettings::default()
+    };
+    let mut buzzer = Buzzer::new(settings, DEFAULT_SAMPLE_RATE);
+    for _ in 0..4 {
+        assert!(frame(&mut cpu, &mut buzzer).iter().all(|&s| s == 0.0));
+    }
+}
+
+#[test]
+fn waveforms_have_the_expected_shape() {
+    for waveform in Waveform::ALL {
+        assert!(waveform.sample(0.0).abs() <= 1.0);
+    }
+    assert_eq!(Waveform::Square.sample(0.25), 1.0);
+    assert_eq!(Waveform::Square.sample(0.75), -1.0);
+    assert!((Waveform::Sine.sample(0.25) - 1.0).abs() < 1e-9);
+    assert_eq!(Waveform::Triangle.sample(0.5), 1.0);
+    assert_eq!(Waveform::Triangle.sample(0.0), -1.0);
+}
+
+#[test]
+fn xo_chip_plays_the_audio_pattern() {
+    let rom = assemble(
+        "
+            LD I, pattern
+            AUDIO
+            LD V0, 6
+            LD ST, V0
+        loop:
+            JP loop
+        pattern:
+            DB 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00
+            DB 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00
+    ",
+    )
+    .unwrap();
+    let settings = BuzzerSettings {
+        waveform: Waveform::Sine,
+        ..BuzzerSettings::default()
+    };
+    let mut cpu = Cpu::builder(600).seed(0).xo_chip(true).build();
+    cpu.load_bytes(&rom).unwrap();
+    let mut buzzer = Buzzer::new(settings, DEFAULT_SAMPLE_RATE);
+    frame(&mut cpu, &mut buzzer);
+    let samples = frame(&mut cpu, &mut buzzer);
+    // The pattern is a square wave, whatever the waveform setting
+    assert!(samples.iter().all(|&s| s == 0.25 || s == -0.25));
+    // 16 bits per period at 4000 bits per second is 250 Hz, so
+    // every half period lasts 8 bits, or 88.2 samples
+    let edges: Vec<usize> = (1..samples.len())
+        .filter(|&n| samples[n] != samples[n - 1])
+        .collect();
+    assert!(edges.len() > 4);
+    for pair in edges.windows(2) {
+        assert!((88..=89).contains(&(pair[1] - pair[0])), "{:?}", edges);
+    }
+
+    // Without a pattern XO-CHIP programs get the plain tone
+    let mut cpu = beeping_cpu(true);
+    let mut buzzer = Buzzer::new(settings, DEFAULT_SAMPLE_RATE);
+    frame(&mut cpu, &mut buzzer);
+    let samples = frame(&mut cpu, &mut buzzer);
+    assert!(samples.iter().any(|&s| s.abs() > 0.0 && s.abs() < 0.2));
+}
+
+#[test]
+fn wav_output_writes_a_readable_file() {
+    let mut cpu = beeping_cpu(false);
+    let mut buzzer = Buzzer::new(BuzzerSettings::default(), 8000);
+    let path = env::temp_dir().join(format!(
+        "chip8_{}_wav_output_writes_a_readable_file.wav",
+        std::process::id()
+    ));
+    let path = path.to_str().unwrap();
+    let mut output = WavOutput::create(path, 8000).unwrap();
+    for _ in 0..3 {
+        cpu.run_frame_with(|cpu| buzzer.cycle(cpu)).unwrap();
+        buzzer.flush(&mut output);
+    }
+    output.finish().unwrap();
+
+    let reader = hound::WavReader::open(path).unwrap();
+    assert_eq!(reader.spec().sample_rate, 8000);
+    assert_eq!(reader.spec().channels, 1);
+    assert_eq!(reader.len(), 3 * 8000 / 60);
+    drop(reader);
+    std::fs::remove_file(path).unwrap();
+}
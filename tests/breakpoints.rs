@@ -0,0 +1,117 @@
+use chip8::asm::assemble;
+use chip8::{BreakEvent, BreakKind, BreakReason, Breakpoint, Cpu, StepOutcome};
+
+// Counts V3 up, drawing the same sprite twice on every pass so
+// that the second draw collides, and stores V3 as BCD at 0x300
+const PROGRAM: &str = "
+          LD I, 0x300
+    loop: ADD V3, 1
+          DRW V0, V0, 1
+          DRW V0, V0, 1
+          LD B, V3
+          JP loop
+";
+
+fn cpu() -> Cpu {
+    let mut cpu = Cpu::builder(600).seed(0).build();
+    cpu.load_bytes(&assemble(PROGRAM).unwrap()).unwrap();
+    cpu
+}
+
+// Ticks until a break, failing after too many cycles
+fn run_to_break(cpu: &mut Cpu) -> BreakReason {
+    for _ in 0..10_000 {
+        if let StepOutcome::Break(reason) = cpu.tick().unwrap() {
+            return reason;
+        }
+    }
+    panic!("no breakpoint was hit");
+}
+
+#[test]
+fn exec_breakpoint_stops_before_instruction_and_resumes() {
+    let mut cpu = cpu();
+    cpu.breakpoints
+        .push(Breakpoint::new(BreakKind::Exec(0x202)));
+    let reason = run_to_break(&mut cpu);
+    assert_eq!(
+        (reason.index, reason.pc, reason.event),
+        (0, 0x202, BreakEvent::Exec)
+    );
+    assert_eq!(cpu.pc(), 0x202);
+    assert_eq!(cpu.registers()[3], 0);
+    // Resuming executes the instruction, and the breakpoint is hit again on the next pass
+    assert_eq!(cpu.tick().unwrap(), StepOutcome::Executed);
+    assert_eq!(cpu.registers()[3], 1);
+    run_to_break(&mut cpu);
+    assert_eq!(cpu.registers()[3], 1);
+}
+
+#[test]
+fn conditional_breakpoint_only_stops_when_condition_holds() {
+    let mut cpu = cpu();
+    cpu.breakpoints
+        .push(Breakpoint::new(BreakKind::Exec(0x208)).when("V3 == 0x10".parse().unwrap()));
+    let reason = run_to_break(&mut cpu);
+    assert_eq!(reason.pc, 0x208);
+    assert_eq!(cpu.registers()[3], 0x10);
+}
+
+#[test]
+fn register_watch_reports_collision_flag() {
+    let mut cpu = cpu();
+    cpu.breakpoints
+        .push("change VF if VF == 1".parse().unwrap());
+    let reason = run_to_break(&mut cpu);
+    assert_eq!(reason.pc, 0x206);
+    assert_eq!(
+        reason.event,
+        BreakEvent::Register {
+            x: 0xF,
+            old: 0,
+            new: 1
+        }
+    );
+    // Watchpoints stop after the instruction
+    assert_eq!(cpu.pc(), 0x208);
+}
+
+#[test]
+fn memory_watches_report_accessed_address() {
+    let mut cpu = cpu();
+    cpu.breakpoints.push("write 0x302".parse().unwrap());
+    let reason = run_to_break(&mut cpu);
+    assert_eq!(
+        (reason.pc, reason.event),
+        (0x208, BreakEvent::Write { addr: 0x302 })
+    );
+
+    // Sprite data is read by DRW
+    let mut cpu = self::cpu();
+    cpu.breakpoints.push("read 0x300-0x30F".parse().unwrap());
+    let reason = run_to_break(&mut cpu);
+    assert_eq!(
+        (reason.pc, reason.event),
+        (0x204, BreakEvent::Read { addr: 0x300 })
+    );
+}
+
+#[test]
+fn breakpoints_parse_as_displayed() {
+    for text in [
+        "exec 0x2A0",
+        "read 0x300-0x30F",
+        "write 0xF00-0xF00 if I == 0xF00",
+        "change VF if VF >= 0x1",
+    ] {
+        let breakpoint: Breakpoint = text.parse().unwrap();
+        assert_eq!(
+            breakpoint.to_string().parse::<Breakpoint>().unwrap(),
+            breakpoint
+        );
+    }
+    assert!("exec".parse::<Breakpoint>().is_err());
+    assert!("change I".parse::<Breakpoint>().is_err());
+    assert!("read 0x30F-0x300".parse::<Breakpoint>().is_err());
+    assert!("exec 0x200 if V3 = 1".parse::<Breakpoint>().is_err());
+}
@@ -0,0 +1,330 @@
+// Runs test ROMs for a fixed number of frames and compares the final
+// screen to golden images in tests/golden. The golden images are the
+// greyscale PNGs written by `chip8 run --headless --png`. Run the tests
+// with CHIP8_BLESS=1 to write the current screens as the new golden images.
+//
+// The golden images are made by the emulator itself, so they only catch
+// changes in behaviour. The IBM logo is also checked against a reference
+// written out from the sprite data of the ROM.
+//
+// The community test suite ROMs (corax+, flags, quirks and keypad tests
+// from Timendus' chip8-test-suite) are not bundled with the repository.
+// CI downloads them into roms/tests and runs their test with
+// `cargo test --test conformance -- --ignored`. Rather than comparing
+// to golden images, it checks the pass and fail marks the ROMs draw
+// next to each of their results. It fails if any of the ROMs is missing.
+use chip8::{Chip8Display, Cpu, Keypad, Quirks, QuirksPreset};
+use std::env;
+use std::fs::{self, File};
+use std::path::Path;
+
+// Grey level of each pixel colour, matching the headless runner
+const PNG_LEVELS: [u8; 4] = [0x00, 0xFF, 0xAA, 0x55];
+
+struct Case {
+    name: &'static str,
+    rom: &'static str,
+    frames: usize,
+    quirks: Quirks,
+    // Frame number, key and whether the key is pressed or released
+    keys: &'static [(usize, usize, bool)],
+}
+
+impl Case {
+    fn new(name: &'static str, rom: &'static str, frames: usize) -> Self {
+        Case {
+            name,
+            rom,
+            frames,
+            quirks: Quirks::default(),
+            keys: &[],
+        }
+    }
+
+    fn keys(self, keys: &'static [(usize, usize, bool)]) -> Self {
+        Case { keys, ..self }
+    }
+
+    fn quirks(self, preset: QuirksPreset) -> Self {
+        Case {
+            quirks: preset.quirks(),
+            ..self
+        }
+    }
+}
+
+fn cases() -> Vec<Case> {
+    vec![
+        Case::new("ibm_logo", "roms/IBM Logo.ch8", 60),
+        // Presses 2 to count up, then 5 to start the delay timer at that count
+        Case::new(
+            "delay_timer",
+            "roms/Delay Timer Test [Matthew Mikolay, 2010].ch8",
+            60,
+        )
+        .keys(&[
+            (10, 0x2, true),
+            (40, 0x2, false),
+            (42, 0x5, true),
+            (44, 0x5, false),
+        ]),
+        Case::new("keypad", "roms/Keypad Test [Hap, 2006].ch8", 120).keys(&[(60, 0x5, true)]),
+        Case::new(
+            "random_number",
+            "roms/Random Number Test [Matthew Mikolay, 2010].ch8",
+            60,
+        ),
+        Case::new("chip8_picture", "roms/Chip8 Picture.ch8", 60),
+        Case::new(
+            "sierpinski",
+            "roms/Sierpinski [Sergey Naydenov, 2010].ch8",
+            300,
+        ),
+    ]
+}
+
+// Cases of the community test suite, found in roms/tests
+fn suite_cases() -> Vec<Case> {
+    vec![
+        Case::new("corax_plus", "roms/tests/3-corax+.ch8", 60),
+        Case::new("flags", "roms/tests/4-flags.ch8", 60),
+        // Selects the CHIP-8 platform from the menu
+        Case::new("quirks", "roms/tests/5-quirks.ch8", 300)
+            .quirks(QuirksPreset::CosmacVip)
+            .keys(&[(10, 0x1, true), (12, 0x1, false)]),
+        // Selects the FX0A test, then presses and releases 5
+        Case::new("keypad_suite", "roms/tests/6-keypad.ch8", 120).keys(&[
+            (10, 0x3, true),
+            (12, 0x3, false),
+            (60, 0x5, true),
+            (70, 0x5, false),
+        ]),
+    ]
+}
+
+// Runs a case, returning the final screen
+fn run(case: &Case, rom: &[u8]) -> Chip8Display {
+    let mut cpu = Cpu::builder(500).seed(0).quirks(case.quirks).build();
+    cpu.load_bytes(rom).unwrap();
+    let mut keypad = Keypad::new();
+    for frame in 0..case.frames {
+        for &(_, key, pressed) in case.keys.iter().filter(|(f, _, _)| *f == frame) {
+            if pressed {
+                keypad.press(key);
+            } else {
+                keypad.release(key);
+            }
+        }
+        cpu.keypad = keypad;
+        cpu.run_frame()
+            .unwrap_or_else(|e| panic!("{}: frame {}: {}", case.name, frame, e));
+    }
+    cpu.display
+}
+
+// Draws a screen as text, like the headless runner does
+fn to_text(width: usize, pixels: &[u8]) -> Vec<String> {
+    pixels
+        .chunks(width)
+        .map(|row| {
+            row.iter()
+                .map(|&p| ['.', '#', '+', '@'][p as usize])
+                .collect()
+        })
+        .collect()
+}
+
+fn read_golden(path: &Path) -> (usize, Vec<u8>) {
+    let decoder = png::Decoder::new(File::open(path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+    let pixels = buf[..info.buffer_size()]
+        .iter()
+        .map(|level| PNG_LEVELS.iter().position(|l| l == level).unwrap() as u8)
+        .collect();
+    (info.width as usize, pixels)
+}
+
+fn write_golden(path: &Path, display: &Chip8Display) {
+    let file = File::create(path).unwrap();
+    let mut encoder = png::Encoder::new(file, display.width() as u32, display.height() as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let pixels: Vec<u8> = display
+        .screen()
+        .iter()
+        .map(|&p| PNG_LEVELS[p as usize])
+        .collect();
+    encoder
+        .write_header()
+        .unwrap()
+        .write_image_data(&pixels)
+        .unwrap();
+}
+
+// Shows the two screens side by side, marking the rows that differ
+fn diff(expected: &[String], actual: &[String]) -> String {
+    let mut out = String::from("   expected | actual\n");
+    for row in 0..expected.len().max(actual.len()) {
+        let e = expected.get(row).map_or("", |s| s.as_str());
+        let a = actual.get(row).map_or("", |s| s.as_str());
+        let mark = if e == a { ' ' } else { '!' };
+        out.push_str(&format!("{}{:>2} {} | {}\n", mark, row, e, a));
+    }
+    out
+}
+
+// Runs a case and compares its screen to the golden image, returning
+// a description of the difference if they differ
+fn check(case: &Case, bless: bool) -> Option<String> {
+    let rom = fs::read(case.rom).unwrap_or_else(|e| panic!("{}: {}", case.rom, e));
+    let display = run(case, &rom);
+    let golden = Path::new("tests/golden").join(format!("{}.png", case.name));
+    if bless {
+        write_golden(&golden, &display);
+        return None;
+    }
+    if !golden.exists() {
+        return Some(format!(
+            "{}: missing {}, run with CHIP8_BLESS=1",
+            case.name,
+            golden.display()
+        ));
+    }
+    let (width, pixels) = read_golden(&golden);
+    if width != display.width() || pixels != display.screen() {
+        let expected = to_text(width, &pixels);
+        let actual = to_text(display.width(), display.screen());
+        return Some(format!(
+            "{} differs from {}\n{}",
+            case.name,
+            golden.display(),
+            diff(&expected, &actual)
+        ));
+    }
+    None
+}
+
+fn check_all(cases: Vec<Case>) {
+    let bless = env::var_os("CHIP8_BLESS").is_some();
+    let failures: Vec<String> = cases.iter().filter_map(|case| check(case, bless)).collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn screens_match_golden_images() {
+    check_all(cases());
+}
+
+// Mark the test suite draws next to a passed check
+const PASS_MARK: [&str; 4] = ["....#", "...#.", "#.#..", ".#..."];
+// Mark the test suite draws next to a failed check
+const FAIL_MARK: [&str; 5] = ["#...#", ".#.#.", "..#..", ".#.#.", "#...#"];
+
+// Counts the places on the screen a mark is drawn at. The pixels
+// around the mark must be off, so that it is not found inside
+// larger shapes
+fn count_marks(screen: &[String], mark: &[&str]) -> usize {
+    let pixel = |row: isize, col: isize| {
+        usize::try_from(row)
+            .ok()
+            .zip(usize::try_from(col).ok())
+            .and_then(|(row, col)| screen.get(row)?.as_bytes().get(col).copied())
+            .is_some_and(|p| p != b'.')
+    };
+    let (height, width) = (mark.len() as isize, mark[0].len() as isize);
+    let lit = |row: isize, col: isize| {
+        (0..height).contains(&row)
+            && (0..width).contains(&col)
+            && mark[row as usize].as_bytes()[col as usize] == b'#'
+    };
+    let rows = screen.len() as isize;
+    let cols = screen.first().map_or(0, |row| row.len()) as isize;
+    let mut count = 0;
+    for top in 0..rows {
+        for left in 0..cols {
+            let matches = (-1..=height)
+                .all(|row| (-1..=width).all(|col| pixel(top + row, left + col) == lit(row, col)));
+            if matches {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+#[test]
+#[ignore = "needs the chip8-test-suite ROMs in roms/tests, downloaded by CI"]
+fn suite_roms_pass_every_check() {
+    let mut failures = vec![];
+    for case in suite_cases() {
+        let rom = fs::read(case.rom).unwrap_or_else(|e| panic!("{}: {}", case.rom, e));
+        let display = run(&case, &rom);
+        let screen = to_text(display.width(), display.screen());
+        let passed = count_marks(&screen, &PASS_MARK);
+        let failed = count_marks(&screen, &FAIL_MARK);
+        if passed == 0 || failed > 0 {
+            failures.push(format!(
+                "{}: {} checks passed, {} failed\n{}",
+                case.name,
+                passed,
+                failed,
+                screen.join("\n")
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+// The IBM logo ROM draws six 8x15 sprites in a row at y = 8, starting
+// at x = 12. Every second row of the sprites is blank, which gives the
+// logo its stripes. Written out by hand from the sprite data at 0x22A
+// and the coordinates in the code of the ROM
+const IBM_LOGO: [&str; 15] = [
+    "............########.#########...#####.........#####............",
+    "................................................................",
+    "............########.###########.######.......######............",
+    "................................................................",
+    "..............####.....###...###...#####.....#####..............",
+    "................................................................",
+    "..............####.....#######.....#######.#######..............",
+    "................................................................",
+    "..............####.....#######.....###.#######.###..............",
+    "................................................................",
+    "..............####.....###...###...###..#####..###..............",
+    "................................................................",
+    "............########.###########.#####...###...#####............",
+    "................................................................",
+    "............########.#########...#####....#....#####............",
+];
+
+#[test]
+fn ibm_logo_matches_reference() {
+    let blank = ".".repeat(64);
+    let mut expected = vec![blank.clone(); 8];
+    expected.extend(IBM_LOGO.iter().map(|row| row.to_string()));
+    expected.resize(32, blank);
+    let case = &cases()[0];
+    let display = run(case, &fs::read(case.rom).unwrap());
+    let actual = to_text(display.width(), display.screen());
+    assert!(expected == actual, "\n{}", diff(&expected, &actual));
+}
+
+#[test]
+fn marks_are_only_found_on_their_own() {
+    let mut screen = vec![".".repeat(20); 10];
+    for (i, row) in PASS_MARK.iter().enumerate() {
+        screen[2 + i].replace_range(3..8, row);
+    }
+    for (i, row) in FAIL_MARK.iter().enumerate() {
+        screen[3 + i].replace_range(12..17, row);
+    }
+    assert_eq!(count_marks(&screen, &PASS_MARK), 1);
+    assert_eq!(count_marks(&screen, &FAIL_MARK), 1);
+    screen[0].replace_range(0..1, "#");
+    assert_eq!(count_marks(&screen, &PASS_MARK), 1);
+    screen[1].replace_range(7..8, "#");
+    assert_eq!(count_marks(&screen, &PASS_MARK), 0);
+}
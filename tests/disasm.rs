@@ -0,0 +1,66 @@
+use chip8::asm::assemble;
+use chip8::disasm::{disassemble, Line};
+
+// Returns the addresses the disassembler took as code
+fn code(source: &str) -> Vec<u16> {
+    let rom = assemble(source).unwrap();
+    disassemble(&rom)
+        .iter()
+        .filter(|line| matches!(line, Line::Code { .. }))
+        .map(Line::addr)
+        .collect()
+}
+
+#[test]
+fn data_after_jp_is_not_code() {
+    let code = code(
+        "
+            JP start
+            DB 0xFF, 0x81
+        start:
+            LD I, 0x202
+        end:
+            JP end
+            DB 0x60, 0x00
+    ",
+    );
+    assert_eq!(code, [0x200, 0x204, 0x206]);
+}
+
+#[test]
+fn skips_reach_both_following_instructions() {
+    let code = code(
+        "
+            SE V0, 1
+            JP first
+            JP second
+        first:
+            JP first
+        second:
+            SKP V1
+            LD I, LONG 0x1234
+            JP second
+    ",
+    );
+    assert_eq!(code, [0x200, 0x202, 0x204, 0x206, 0x208, 0x20A, 0x20E]);
+}
+
+#[test]
+fn sys_and_jump_tables_are_followed() {
+    let code = code(
+        "
+            SYS target
+            DB 0xFF, 0xFF
+        target:
+            JP V0, table
+            DB 0xFF, 0xFF
+        table:
+            JP one
+            JP two
+            DB 0xFF, 0xFF
+        one: JP one
+        two: JP two
+    ",
+    );
+    assert_eq!(code, [0x200, 0x204, 0x208, 0x20A, 0x20E, 0x210]);
+}
@@ -0,0 +1,106 @@
+// Checks that faults in the running program are reported as the
+// matching Chip8Error, at the instruction that caused them
+use chip8::cpu::RAM_SIZE;
+use chip8::{Chip8Error, Cpu};
+
+// Loads a program given as opcodes
+fn cpu(opcodes: &[u16]) -> Cpu {
+    let rom: Vec<u8> = opcodes.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut cpu = Cpu::builder(500).seed(0).build();
+    cpu.load_bytes(&rom).unwrap();
+    cpu
+}
+
+// Runs a program until it faults, returning the error
+fn fault(opcodes: &[u16]) -> Chip8Error {
+    let mut cpu = cpu(opcodes);
+    for _ in 0..100 {
+        if let Err(e) = cpu.tick() {
+            return e;
+        }
+    }
+    panic!("program did not fault");
+}
+
+#[test]
+fn seventeenth_nested_call_overflows_the_stack() {
+    // Calls itself forever
+    let mut cpu = cpu(&[0x2200]);
+    for _ in 0..16 {
+        cpu.tick().unwrap();
+    }
+    assert_eq!(cpu.tick(), Err(Chip8Error::StackOverflow { addr: 0x200 }));
+}
+
+#[test]
+fn ret_with_empty_stack_underflows() {
+    assert_eq!(fault(&[0x00EE]), Chip8Error::StackUnderflow { addr: 0x200 });
+}
+
+#[test]
+fn undecodable_opcode_is_illegal() {
+    assert_eq!(
+        fault(&[0x00E0, 0x5121]),
+        Chip8Error::IllegalOpcode {
+            addr: 0x202,
+            opcode: 0x5121
+        }
+    );
+}
+
+#[test]
+fn memory_accesses_past_the_end_of_ram_fault() {
+    let end = RAM_SIZE;
+    let cases = [
+        // DRW of 5 rows from the last byte of RAM
+        (0xD005, end),
+        // BCD of 255
+        (0xF033, end),
+        // Store and load of V0 and V1
+        (0xF155, end),
+        (0xF165, end),
+    ];
+    for (opcode, addr) in cases {
+        assert_eq!(
+            fault(&[0x60FF, 0xA000 | (end as u16 - 1), opcode]),
+            Chip8Error::MemoryOutOfBounds { addr },
+            "{:04X}",
+            opcode
+        );
+    }
+}
+
+#[test]
+fn font_digit_above_f_is_invalid() {
+    assert_eq!(
+        fault(&[0x6010, 0xF029]),
+        Chip8Error::InvalidFontDigit { digit: 0x10 }
+    );
+    assert_eq!(
+        fault(&[0x6011, 0xF030]),
+        Chip8Error::InvalidFontDigit { digit: 0x11 }
+    );
+}
+
+#[test]
+fn oversized_rom_is_too_large() {
+    let size = RAM_SIZE - 0x200 + 1;
+    let mut cpu = Cpu::builder(500).build();
+    assert_eq!(
+        cpu.load_bytes(&vec![0; size]),
+        Err(Chip8Error::RomTooLarge { size })
+    );
+
+    let path = std::env::temp_dir().join(format!(
+        "chip8_{}_oversized_rom_is_too_large.ch8",
+        std::process::id()
+    ));
+    std::fs::write(&path, vec![0; size]).unwrap();
+    let result = cpu.load_rom(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+    let error = result.unwrap_err();
+    assert_eq!(
+        error.get_ref().and_then(|e| e.downcast_ref::<Chip8Error>()),
+        Some(&Chip8Error::RomTooLarge { size })
+    );
+}
@@ -0,0 +1,16 @@
+#!/bin/sh
+# Downloads the ROMs of Timendus' chip8-test-suite into roms/tests, for
+# the suite test in tests/conformance.rs. The ROMs are not bundled with
+# this repository, as they are distributed under their own licence,
+# which is downloaded next to them.
+set -eu
+
+VERSION=v4.1
+URL=https://raw.githubusercontent.com/Timendus/chip8-test-suite/$VERSION
+DIR=roms/tests
+
+mkdir -p "$DIR"
+curl -fsSL -o "$DIR/LICENSE" "$URL/LICENSE"
+for rom in 3-corax+ 4-flags 5-quirks 6-keypad; do
+    curl -fsSL -o "$DIR/$rom.ch8" "$URL/bin/$(echo "$rom" | sed 's/+/%2B/').ch8"
+done
@@ -0,0 +1,141 @@
+// Drives the GDB stub with a scripted client speaking the remote
+// serial protocol, like GDB does, without needing GDB installed
+use chip8::asm::assemble;
+use chip8::{Cpu, GdbStub};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+// Counts V3 up forever, storing it as BCD at 0x300
+const PROGRAM: &str = "
+          LD I, 0x300
+    loop: ADD V3, 1
+          LD B, V3
+          JP loop
+";
+
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0];
+        self.stream.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+
+    // Sends a packet and returns the reply, acknowledging both
+    fn send(&mut self, data: &str) -> String {
+        let sum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        write!(self.stream, "${}#{:02x}", data, sum).unwrap();
+        assert_eq!(self.read_byte(), b'+');
+        self.reply()
+    }
+
+    fn reply(&mut self) -> String {
+        assert_eq!(self.read_byte(), b'$');
+        let mut reply = vec![];
+        loop {
+            match self.read_byte() {
+                b'#' => break,
+                b => reply.push(b),
+            }
+        }
+        let sum = [self.read_byte(), self.read_byte()];
+        let sum = u8::from_str_radix(std::str::from_utf8(&sum).unwrap(), 16).unwrap();
+        assert_eq!(sum, reply.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)));
+        self.stream.write_all(b"+").unwrap();
+        String::from_utf8(reply).unwrap()
+    }
+}
+
+#[test]
+fn scripted_session() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let mut cpu = Cpu::builder(600).seed(0).build();
+        cpu.load_bytes(&assemble(PROGRAM).unwrap()).unwrap();
+        GdbStub::accept(&listener).unwrap().serve(&mut cpu).unwrap();
+        cpu
+    });
+    let stream = TcpStream::connect(addr).unwrap();
+    stream.set_nodelay(true).unwrap();
+    let mut gdb = Client { stream };
+
+    assert!(gdb
+        .send("qSupported:xmlRegisters=i386")
+        .contains("qXfer:features:read+"));
+    assert!(gdb
+        .send("qXfer:features:read:target.xml:0,1000")
+        .contains("name=\"pc\""));
+    assert_eq!(gdb.send("?"), "S05");
+    // V0-VF, I, PC, SP, DT and ST
+    assert_eq!(
+        gdb.send("g"),
+        format!("{}0000{}000000", "00".repeat(16), "0200")
+    );
+
+    // Software breakpoint on the jump, hit once every pass
+    assert_eq!(gdb.send("Z0,206,2"), "OK");
+    assert_eq!(gdb.send("c"), "S05");
+    assert_eq!(gdb.send("p11"), "0206");
+    assert_eq!(gdb.send("p3"), "01");
+    assert_eq!(gdb.send("c"), "S05");
+    assert_eq!(gdb.send("p3"), "02");
+    assert_eq!(gdb.send("m300,3"), "000002");
+    assert_eq!(gdb.send("z0,206,2"), "OK");
+
+    // Single step over the jump
+    assert_eq!(gdb.send("s"), "S05");
+    assert_eq!(gdb.send("p11"), "0202");
+
+    // Write watchpoint on the ones digit
+    assert_eq!(gdb.send("Z2,302,1"), "OK");
+    assert_eq!(gdb.send("c"), "T05watch:302;");
+    assert_eq!(gdb.send("m302,1"), "03");
+    assert_eq!(gdb.send("z2,302,1"), "OK");
+
+    // Registers and memory can be written
+    assert_eq!(gdb.send("P3=10"), "OK");
+    assert_eq!(gdb.send("p3"), "10");
+    assert_eq!(gdb.send("M400,2:abcd"), "OK");
+    assert_eq!(gdb.send("m400,2"), "abcd");
+    assert_eq!(gdb.send("mffff,2"), "E01");
+
+    // Ranges that overflow are refused rather than crashing the stub
+    assert_eq!(gdb.send("m1,ffffffffffffffff"), "E01");
+    assert_eq!(gdb.send("Mffffffffffffffff,1:00"), "E01");
+    assert_eq!(gdb.send("M1,ffffffffffffffff:00"), "E01");
+    assert_eq!(gdb.send("Z2,ffffffffffffffff,2"), "E01");
+    assert_eq!(gdb.send("Z3,1,ffffffffffffffff"), "E01");
+    assert_eq!(gdb.send("Z0,10000,2"), "E01");
+    assert_eq!(gdb.send("z2,1,ffffffffffffffff"), "E01");
+    assert_eq!(
+        gdb.send("qXfer:features:read:target.xml:1,ffffffffffffffff"),
+        "E01"
+    );
+    assert_eq!(gdb.send("Z1,200,2"), "");
+    assert_eq!(gdb.send("m400,2"), "abcd");
+
+    // Interrupting a running program
+    write!(gdb.stream, "$c#63").unwrap();
+    assert_eq!(gdb.read_byte(), b'+');
+    gdb.stream.write_all(&[0x03]).unwrap();
+    assert_eq!(gdb.reply(), "S02");
+
+    // A packet sent while running is answered after the stop
+    write!(gdb.stream, "$c#63").unwrap();
+    assert_eq!(gdb.read_byte(), b'+');
+    write!(gdb.stream, "$?#3f").unwrap();
+    gdb.stream.write_all(&[0x03]).unwrap();
+    assert_eq!(gdb.reply(), "S02");
+    assert_eq!(gdb.read_byte(), b'+');
+    assert_eq!(gdb.reply(), "S02");
+
+    assert_eq!(gdb.send("D"), "OK");
+    let cpu = server.join().unwrap();
+    assert_eq!(cpu.memory()[0x400..0x402], [0xAB, 0xCD]);
+    assert!(cpu.breakpoints.is_empty());
+}
@@ -0,0 +1,105 @@
+use std::process::Command;
+
+#[test]
+fn headless_run_prints_screen_and_registers() {
+    let output = Command::new(env!("CARGO_BIN_EXE_chip8"))
+        .args(["run", "--headless", "--frames", "60", "roms/IBM Logo.ch8"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 32 + 4);
+    assert_eq!(
+        lines[8],
+        "............########.#########...#####.........#####............"
+    );
+    assert!(lines[34].starts_with("PC=0228 I=0275"));
+}
+
+#[test]
+fn rewind_memory_too_large_is_rejected() {
+    let output = Command::new(env!("CARGO_BIN_EXE_chip8"))
+        .args([
+            "run",
+            "--headless",
+            "--rewind-memory",
+            &usize::MAX.to_string(),
+        ])
+        .arg("roms/IBM Logo.ch8")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Invalid number"), "{}", stderr);
+}
+
+#[test]
+fn headless_script_errors_report_line() {
+    let script = std::env::temp_dir().join(format!(
+        "chip8_{}_headless_script_errors_report_line.txt",
+        std::process::id()
+    ));
+    std::fs::write(&script, "0 press 5\n10 hold 5\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_chip8"))
+        .args(["run", "--headless", "--input"])
+        .arg(&script)
+        .arg("roms/IBM Logo.ch8")
+        .output()
+        .unwrap();
+    std::fs::remove_file(&script).unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("line 2: Expected press or release"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn headless_wav_is_deterministic() {
+    let rom = chip8::asm::assemble(
+        "
+            LD V0, 30
+            LD ST, V0
+        loop:
+            JP loop
+    ",
+    )
+    .unwrap();
+    let dir = std::env::temp_dir();
+    let temp = |name: &str| {
+        dir.join(format!(
+            "chip8_{}_headless_wav_is_deterministic_{}",
+            std::process::id(),
+            name
+        ))
+    };
+    let rom_path = temp("beep.ch8");
+    std::fs::write(&rom_path, rom).unwrap();
+    let record = |name: &str| {
+        let wav = temp(name);
+        let output = Command::new(env!("CARGO_BIN_EXE_chip8"))
+            .args(["run", "--headless", "--frames", "60", "--wav"])
+            .arg(&wav)
+            .arg(&rom_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let data = std::fs::read(&wav).unwrap();
+        std::fs::remove_file(&wav).unwrap();
+        data
+    };
+    let first = record("1.wav");
+    let second = record("2.wav");
+    std::fs::remove_file(&rom_path).unwrap();
+    assert_eq!(first, second);
+
+    let reader = hound::WavReader::new(first.as_slice()).unwrap();
+    let samples: Vec<i16> = reader.into_samples().map(Result::unwrap).collect();
+    assert_eq!(samples.len(), 44100);
+    // Half a second of tone, then silence
+    assert!(samples[..22000].iter().any(|&s| s != 0));
+    assert!(samples[22100..].iter().all(|&s| s == 0));
+}
@@ -0,0 +1,110 @@
+use chip8::{Cpu, DecodeError, Instruction, Register};
+
+fn cpu() -> Cpu {
+    Cpu::builder(500).seed(0).build()
+}
+
+// Sets V registers from (index, value) pairs
+fn set(cpu: &mut Cpu, values: &[(u8, u8)]) {
+    for &(x, value) in values {
+        cpu.set_register(Register::V(x), value as u16);
+    }
+}
+
+#[test]
+fn opcodes_decode_to_instructions() {
+    let cases = [
+        (0x00E0, Instruction::Cls),
+        (0x00EE, Instruction::Ret),
+        (0x0123, Instruction::Sys(0x123)),
+        (0x1ABC, Instruction::Jp(0xABC)),
+        (0x2ABC, Instruction::Call(0xABC)),
+        (0x3A12, Instruction::SeByte { x: 0xA, kk: 0x12 }),
+        (0x5120, Instruction::SeReg { x: 1, y: 2 }),
+        (0x8124, Instruction::AddReg { x: 1, y: 2 }),
+        (0x812E, Instruction::Shl { x: 1, y: 2 }),
+        (0x9120, Instruction::SneReg { x: 1, y: 2 }),
+        (0xD125, Instruction::Drw { x: 1, y: 2, n: 5 }),
+        (0xE19E, Instruction::Skp { x: 1 }),
+        (0xF133, Instruction::LdBcd { x: 1 }),
+        (0xF265, Instruction::LoadRegs { x: 2 }),
+    ];
+    for (opcode, inst) in cases {
+        assert_eq!(Instruction::decode(opcode), Ok(inst), "{:04X}", opcode);
+        assert_eq!(inst.encode(), opcode);
+    }
+}
+
+#[test]
+fn illegal_opcodes_are_rejected() {
+    for opcode in [
+        0x5121, 0x812F, 0x8128, 0x9121, 0xE000, 0xE0FF, 0xF199, 0xF401,
+    ] {
+        assert_eq!(
+            Instruction::decode(opcode),
+            Err(DecodeError { opcode }),
+            "{:04X}",
+            opcode
+        );
+    }
+}
+
+#[test]
+fn arithmetic_sets_vf_after_the_result() {
+    let mut cpu = cpu();
+    // 8xy4 carry
+    set(&mut cpu, &[(1, 0xFF), (2, 0x02)]);
+    cpu.execute(Instruction::AddReg { x: 1, y: 2 }).unwrap();
+    assert_eq!((cpu.registers()[1], cpu.registers()[0xF]), (0x01, 1));
+    // With Vx = VF the flag wins over the result
+    set(&mut cpu, &[(0xF, 0xFF), (1, 0x01)]);
+    cpu.execute(Instruction::AddReg { x: 0xF, y: 1 }).unwrap();
+    assert_eq!(cpu.registers()[0xF], 1);
+
+    // 8xy5 sets VF when there is no borrow
+    set(&mut cpu, &[(1, 5), (2, 3)]);
+    cpu.execute(Instruction::Sub { x: 1, y: 2 }).unwrap();
+    assert_eq!((cpu.registers()[1], cpu.registers()[0xF]), (2, 1));
+    set(&mut cpu, &[(1, 3), (2, 5)]);
+    cpu.execute(Instruction::Sub { x: 1, y: 2 }).unwrap();
+    assert_eq!((cpu.registers()[1], cpu.registers()[0xF]), (0xFE, 0));
+
+    // 8xy7 subtracts Vx from Vy
+    set(&mut cpu, &[(1, 3), (2, 5)]);
+    cpu.execute(Instruction::Subn { x: 1, y: 2 }).unwrap();
+    assert_eq!((cpu.registers()[1], cpu.registers()[0xF]), (2, 1));
+    set(&mut cpu, &[(0xF, 5), (2, 3)]);
+    cpu.execute(Instruction::Subn { x: 0xF, y: 2 }).unwrap();
+    assert_eq!(cpu.registers()[0xF], 0);
+
+    // 8xy6 and 8xyE shift out into VF
+    set(&mut cpu, &[(1, 0x81)]);
+    cpu.execute(Instruction::Shr { x: 1, y: 2 }).unwrap();
+    assert_eq!((cpu.registers()[1], cpu.registers()[0xF]), (0x40, 1));
+    set(&mut cpu, &[(1, 0x81)]);
+    cpu.execute(Instruction::Shl { x: 1, y: 2 }).unwrap();
+    assert_eq!((cpu.registers()[1], cpu.registers()[0xF]), (0x02, 1));
+    set(&mut cpu, &[(0xF, 0x02)]);
+    cpu.execute(Instruction::Shr { x: 0xF, y: 2 }).unwrap();
+    assert_eq!(cpu.registers()[0xF], 0);
+}
+
+#[test]
+fn bcd_and_register_transfers_use_memory_at_i() {
+    let mut cpu = cpu();
+    set(&mut cpu, &[(3, 254)]);
+    cpu.set_register(Register::I, 0x300);
+    cpu.execute(Instruction::LdBcd { x: 3 }).unwrap();
+    assert_eq!(&cpu.memory()[0x300..0x303], [2, 5, 4]);
+
+    set(&mut cpu, &[(0, 0x10), (1, 0x11), (2, 0x12), (3, 0x13)]);
+    cpu.execute(Instruction::StoreRegs { x: 2 }).unwrap();
+    // V3 is past x and stays out of memory
+    assert_eq!(&cpu.memory()[0x300..0x304], [0x10, 0x11, 0x12, 0]);
+    assert_eq!(cpu.i(), 0x300);
+
+    cpu.write_memory(0x310, &[0xA0, 0xA1, 0xA2]).unwrap();
+    cpu.set_register(Register::I, 0x310);
+    cpu.execute(Instruction::LoadRegs { x: 1 }).unwrap();
+    assert_eq!(&cpu.registers()[..3], [0xA0, 0xA1, 0x12]);
+}
@@ -0,0 +1,86 @@
+use chip8::asm::assemble;
+use chip8::{Chip8Error, Cpu, Keypad, Movie, MovieError};
+
+// Moves a random sprite around with keys 5, 7, 8 and 9
+const PROGRAM: &str = "
+          RND V2, 0x0F
+          LD F, V2
+    loop: DRW V0, V1, 5
+          LD V3, 5
+          SKNP V3
+          ADD V1, 0xFF
+          LD V3, 8
+          SKNP V3
+          ADD V1, 1
+          LD V3, 7
+          SKNP V3
+          ADD V0, 0xFF
+          LD V3, 9
+          SKNP V3
+          ADD V0, 1
+          DRW V0, V1, 5
+          JP loop
+";
+
+#[test]
+fn recorded_session_replays_exactly() {
+    let rom = assemble(PROGRAM).unwrap();
+    let mut cpu = Cpu::builder(600).seed(7).build();
+    cpu.load_bytes(&rom).unwrap();
+
+    let mut movie = Movie::new(&cpu);
+    for frame in 0..120_u16 {
+        // Hold a different direction every 10 frames
+        let keypad = Keypad::from_bits(1 << [5, 8, 7, 9][(frame / 10 % 4) as usize]);
+        movie.record(&keypad);
+        cpu.keypad = keypad;
+        cpu.run_frame().unwrap();
+    }
+    let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+
+    // Replay on a CPU with other settings, the movie restores them
+    let mut replay = Cpu::new(500);
+    movie.start(&mut replay, &rom).unwrap();
+    for keypad in &movie.frames {
+        replay.keypad = *keypad;
+        replay.run_frame().unwrap();
+    }
+    assert_eq!(replay.display, cpu.display);
+    assert_eq!(replay.save_state(), cpu.save_state());
+
+    assert!(matches!(
+        movie.start(&mut replay, &assemble("CLS").unwrap()),
+        Err(MovieError::RomMismatch { .. })
+    ));
+}
+
+#[test]
+fn invalid_movies_report_movie_errors() {
+    let rom = assemble("CLS").unwrap();
+    let mut cpu = Cpu::new(500);
+    cpu.load_bytes(&rom).unwrap();
+    let data = Movie::new(&cpu).to_bytes();
+
+    let error = Movie::from_bytes(b"C8ST").unwrap_err();
+    assert_eq!(error, MovieError::BadMagic);
+    assert_eq!(error.to_string(), "Not a CHIP-8 movie");
+    assert_eq!(
+        Movie::from_bytes(&data[..data.len() - 1]),
+        Err(MovieError::Truncated)
+    );
+    let mut newer = data.clone();
+    newer[4..6].copy_from_slice(&99_u16.to_le_bytes());
+    assert_eq!(
+        Movie::from_bytes(&newer),
+        Err(MovieError::UnsupportedVersion { version: 99 })
+    );
+
+    // A ROM that can not be loaded is reported as such
+    let mut movie = Movie::new(&cpu);
+    let too_large = vec![0; 4096];
+    movie.rom_hash = chip8::state::rom_hash(&too_large);
+    assert_eq!(
+        movie.start(&mut cpu, &too_large),
+        Err(MovieError::Rom(Chip8Error::RomTooLarge { size: 4096 }))
+    );
+}
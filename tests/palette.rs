@@ -0,0 +1,61 @@
+use chip8::palette::{format_color, parse_color};
+use chip8::{Config, Palette, PalettePreset};
+use std::env;
+
+#[test]
+fn hex_colours_parse_and_format() {
+    assert_eq!(parse_color("#9BBC0F"), Ok([0x9B, 0xBC, 0x0F]));
+    assert_eq!(parse_color("ff8000"), Ok([0xFF, 0x80, 0x00]));
+    assert!(parse_color("#FFF").is_err());
+    assert!(parse_color("#GGGGGG").is_err());
+    assert!(parse_color("+FFFFF").is_err());
+    assert_eq!(format_color([0x0F, 0x38, 0x0F]), "#0F380F");
+}
+
+#[test]
+fn palettes_round_trip_and_match_presets() {
+    for preset in PalettePreset::ALL {
+        let palette = preset.palette();
+        assert_eq!(palette.preset(), Some(preset));
+        assert_eq!(palette.to_string().parse::<Palette>(), Ok(palette));
+    }
+    let gameboy: Palette = "gameboy".parse().unwrap();
+    assert_eq!(gameboy, PalettePreset::GameBoy.palette());
+    assert_eq!(
+        gameboy.rgba(1),
+        [15.0 / 255.0, 56.0 / 255.0, 15.0 / 255.0, 1.0]
+    );
+
+    let custom: Palette = "#000000 #FF0000 #00FF00 #0000FF".parse().unwrap();
+    assert_eq!(custom.preset(), None);
+    assert_eq!(custom.colors[3], [0, 0, 0xFF]);
+    assert!("#000000 #FF0000".parse::<Palette>().is_err());
+}
+
+#[test]
+fn config_is_saved_and_loaded() {
+    let source = "# settings\n\npalette = amber\n";
+    let config: Config = source.parse().unwrap();
+    assert_eq!(config.palette, PalettePreset::Amber.palette());
+    assert_eq!(
+        "palette = #000000\n".parse::<Config>().unwrap_err(),
+        "line 1: Expected a palette name or four colours, got #000000"
+    );
+    assert_eq!(
+        "\ncolour = red\n".parse::<Config>().unwrap_err(),
+        "line 2: Unknown setting colour"
+    );
+
+    let dir = env::temp_dir().join(format!(
+        "chip8_{}_config_is_saved_and_loaded",
+        std::process::id()
+    ));
+    let path = dir.join("config");
+    assert_eq!(Config::load(&path).unwrap(), Config::default());
+    let config = Config {
+        palette: "#010203 #040506 #070809 #0A0B0C".parse().unwrap(),
+    };
+    config.save(&path).unwrap();
+    assert_eq!(Config::load(&path).unwrap(), config);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,131 @@
+use chip8::asm::assemble;
+use chip8::{Cpu, Instruction, Quirks, QuirksPreset, Register};
+
+fn cpu(quirks: Quirks) -> Cpu {
+    Cpu::builder(500).seed(0).quirks(quirks).build()
+}
+
+// Runs an instruction with the flag off and on, returning both CPUs
+fn both(set: fn(&mut Quirks), setup: fn(&mut Cpu), inst: Instruction) -> (Cpu, Cpu) {
+    let mut quirks = Quirks::default();
+    let mut off = cpu(quirks);
+    set(&mut quirks);
+    let mut on = cpu(quirks);
+    for cpu in [&mut off, &mut on] {
+        setup(cpu);
+        cpu.execute(inst).unwrap();
+    }
+    (off, on)
+}
+
+#[test]
+fn shift_uses_vy() {
+    let (off, on) = both(
+        |q| q.shift_uses_vy = true,
+        |cpu| {
+            cpu.set_register(Register::V(1), 0x10);
+            cpu.set_register(Register::V(2), 0x03);
+        },
+        Instruction::Shr { x: 1, y: 2 },
+    );
+    assert_eq!((off.registers()[1], off.registers()[0xF]), (0x08, 0));
+    assert_eq!((on.registers()[1], on.registers()[0xF]), (0x01, 1));
+}
+
+#[test]
+fn load_store_increments_i() {
+    let setup = |cpu: &mut Cpu| cpu.set_register(Register::I, 0x300);
+    let store = Instruction::StoreRegs { x: 3 };
+    let (off, on) = both(|q| q.load_store_increments_i = true, setup, store);
+    assert_eq!((off.i(), on.i()), (0x300, 0x304));
+
+    // CHIP-48 stops one short, at the last register
+    let (_, by_x) = both(
+        |q| {
+            q.load_store_increments_i = true;
+            q.load_store_increments_by_x = true;
+        },
+        setup,
+        Instruction::LoadRegs { x: 3 },
+    );
+    assert_eq!(by_x.i(), 0x303);
+    let mut chip48 = cpu(QuirksPreset::Chip48.quirks());
+    setup(&mut chip48);
+    chip48.execute(store).unwrap();
+    assert_eq!(chip48.i(), 0x303);
+    let mut vip = cpu(QuirksPreset::CosmacVip.quirks());
+    setup(&mut vip);
+    vip.execute(store).unwrap();
+    assert_eq!(vip.i(), 0x304);
+}
+
+#[test]
+fn jump_uses_vx() {
+    let (off, on) = both(
+        |q| q.jump_uses_vx = true,
+        |cpu| {
+            cpu.set_register(Register::V(0), 0x02);
+            cpu.set_register(Register::V(3), 0x10);
+        },
+        Instruction::JpV0(0x300),
+    );
+    assert_eq!((off.pc(), on.pc()), (0x302, 0x310));
+}
+
+#[test]
+fn vf_reset() {
+    let (off, on) = both(
+        |q| q.vf_reset = true,
+        |cpu| {
+            cpu.set_register(Register::V(0xF), 0x55);
+            cpu.set_register(Register::V(1), 0x0F);
+        },
+        Instruction::Or { x: 1, y: 2 },
+    );
+    assert_eq!((off.registers()[0xF], on.registers()[0xF]), (0x55, 0));
+}
+
+#[test]
+fn clip_sprites() {
+    // An 8x2 sprite drawn at the bottom right corner
+    let (off, on) = both(
+        |q| q.clip_sprites = true,
+        |cpu| {
+            cpu.set_register(Register::V(1), 60);
+            cpu.set_register(Register::V(2), 31);
+            cpu.set_register(Register::I, 0x300);
+            cpu.write_memory(0x300, &[0xFF, 0xFF]).unwrap();
+        },
+        Instruction::Drw { x: 1, y: 2, n: 2 },
+    );
+    // Wrapped pixels appear at the opposite edges
+    assert!(off.display.pixel(63, 31) && off.display.pixel(0, 31) && off.display.pixel(0, 0));
+    assert!(on.display.pixel(63, 31) && !on.display.pixel(0, 31) && !on.display.pixel(63, 0));
+}
+
+#[test]
+fn display_wait() {
+    let rom = assemble(
+        "
+            DRW V0, V0, 1
+            DRW V0, V0, 1
+            DRW V0, V0, 1
+    ",
+    )
+    .unwrap();
+    let run = |quirks: Quirks| {
+        let mut cpu = cpu(quirks);
+        cpu.load_bytes(&rom).unwrap();
+        for _ in 0..3 {
+            cpu.tick().unwrap();
+        }
+        cpu.pc()
+    };
+    let quirks = Quirks {
+        display_wait: true,
+        ..Quirks::default()
+    };
+    // The frame starts after the first cycle, so the second
+    // DRW draws and the third waits for the next frame
+    assert_eq!((run(Quirks::default()), run(quirks)), (0x206, 0x204));
+}
@@ -0,0 +1,70 @@
+use chip8::asm::assemble;
+use chip8::{Cpu, Rewind};
+
+#[test]
+fn depth_limit_drops_the_oldest_frames() {
+    // One second at 3 frames per second keeps 3 frames
+    let mut rewind = Rewind::new(1, 3, usize::MAX);
+    for frame in 0..5 {
+        rewind.push(vec![frame]);
+    }
+    assert_eq!(rewind.len(), 3);
+    assert_eq!(rewind.pop(), Some(vec![4]));
+    assert_eq!(rewind.pop(), Some(vec![3]));
+    assert_eq!(rewind.pop(), Some(vec![2]));
+    assert_eq!(rewind.pop(), None);
+
+    // Lowering the depth evicts right away
+    for frame in 0..3 {
+        rewind.push(vec![frame]);
+    }
+    rewind.set_depth(1);
+    assert_eq!(rewind.pop(), Some(vec![2]));
+    assert!(rewind.is_empty());
+}
+
+#[test]
+fn memory_budget_drops_the_oldest_frames() {
+    let mut rewind = Rewind::new(10, 60, 10);
+    for frame in 0..4 {
+        rewind.push(vec![frame; 4]);
+    }
+    // Only two 4 byte frames fit in 10 bytes
+    assert_eq!(rewind.len(), 2);
+    assert_eq!(rewind.pop(), Some(vec![3; 4]));
+    assert_eq!(rewind.pop(), Some(vec![2; 4]));
+    assert_eq!(rewind.pop(), None);
+
+    // A frame bigger than the budget is not kept at all
+    rewind.push(vec![0; 11]);
+    assert!(rewind.is_empty());
+}
+
+#[test]
+fn stepping_back_restores_earlier_frames() {
+    let rom = assemble(
+        "
+        loop:
+            ADD V0, 1
+            JP loop
+    ",
+    )
+    .unwrap();
+    let mut cpu = Cpu::builder(600).seed(0).build();
+    cpu.load_bytes(&rom).unwrap();
+    let state_size = cpu.save_state().len();
+    // Room for 3 frames by depth, but only 2 by memory
+    let mut rewind = Rewind::new(1, 3, state_size * 2);
+    let mut history = vec![];
+    for _ in 0..6 {
+        cpu.run_frame().unwrap();
+        history.push(cpu.registers()[0]);
+        rewind.push(cpu.save_state());
+    }
+    assert_eq!(rewind.len(), 2);
+    for expected in [history[5], history[4]] {
+        cpu.load_state(&rewind.pop().unwrap()).unwrap();
+        assert_eq!(cpu.registers()[0], expected);
+    }
+    assert_eq!(rewind.pop(), None);
+}
@@ -0,0 +1,81 @@
+use chip8::asm::assemble;
+use chip8::{Cpu, SpriteKind, SpriteSheet};
+use std::env;
+use std::fs::File;
+
+// Draws the sheet as text, one row of pixels per line
+fn rows(sheet: &SpriteSheet) -> Vec<String> {
+    sheet
+        .pixels
+        .chunks(sheet.width)
+        .map(|row| row.iter().map(|&lit| if lit { '#' } else { '.' }).collect())
+        .collect()
+}
+
+#[test]
+fn font_renders_as_sprites() {
+    let cpu = Cpu::builder(500).seed(0).build();
+    let sheet = SpriteSheet::new(cpu.memory(), 0, 2, SpriteKind::Chip8 { height: 5 });
+    assert_eq!((sheet.width, sheet.height), (17, 5));
+    assert_eq!(
+        rows(&sheet),
+        [
+            "####.......#.....",
+            "#..#......##.....",
+            "#..#.......#.....",
+            "#..#.......#.....",
+            "####......###....",
+        ]
+    );
+    assert_eq!(sheet.sprites[1].addr, 5);
+    assert_eq!((sheet.sprites[1].x, sheet.sprites[1].y), (9, 0));
+}
+
+#[test]
+fn sheet_wraps_rows_and_draws_super_chip_sprites() {
+    let cpu = Cpu::builder(500).seed(0).build();
+    let sheet = SpriteSheet::new(cpu.memory(), 0, 10, SpriteKind::Chip8 { height: 5 });
+    assert_eq!((sheet.width, sheet.height), (8 * 9 - 1, 2 * 6 - 1));
+    assert_eq!((sheet.sprites[8].x, sheet.sprites[8].y), (0, 6));
+
+    let sheet = SpriteSheet::new(cpu.memory(), 0x50, 1, SpriteKind::SuperChip);
+    assert_eq!((sheet.width, sheet.height), (16, 16));
+    // The big font digits are 8 pixels wide, so every row pairs two of their rows
+    assert_eq!(rows(&sheet)[0], "################");
+}
+
+#[test]
+fn last_drw_read_is_tracked_and_sheet_exports_png() {
+    let rom = assemble(
+        "
+            LD I, sprite
+            DRW V0, V0, 3
+        sprite:
+            DB 0x81, 0x42, 0x24
+    ",
+    )
+    .unwrap();
+    let mut cpu = Cpu::builder(500).seed(0).build();
+    cpu.load_bytes(&rom).unwrap();
+    assert_eq!(cpu.last_sprite(), None);
+    cpu.tick().unwrap();
+    cpu.tick().unwrap();
+    assert_eq!(cpu.last_sprite(), Some(0x204..0x207));
+
+    let sheet = SpriteSheet::new(cpu.memory(), 0x204, 1, SpriteKind::Chip8 { height: 3 });
+    assert_eq!(rows(&sheet), ["#......#", ".#....#.", "..#..#.."]);
+    let path = env::temp_dir().join(format!(
+        "chip8_{}_last_drw_read_is_tracked_and_sheet_exports_png.png",
+        std::process::id()
+    ));
+    sheet.write_png(path.to_str().unwrap()).unwrap();
+    let mut reader = png::Decoder::new(File::open(&path).unwrap())
+        .read_info()
+        .unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    assert_eq!((info.width, info.height), (8, 3));
+    assert_eq!(&buf[..8], [0xFF, 0, 0, 0, 0, 0, 0, 0xFF]);
+    drop(reader);
+    std::fs::remove_file(&path).unwrap();
+}
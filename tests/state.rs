@@ -0,0 +1,73 @@
+use chip8::asm::assemble;
+use chip8::{Cpu, StateError};
+
+const PROGRAM: &str = "
+        LD V0, 0
+        LD I, sprite
+    loop: DRW V0, V0, 1
+        ADD V0, 1
+        JP loop
+    sprite: DB \"#.......\"
+";
+
+#[test]
+fn state_round_trips() {
+    let rom = assemble(PROGRAM).unwrap();
+    let mut cpu = Cpu::new(500);
+    cpu.load_bytes(&rom).unwrap();
+    for _ in 0..8 {
+        cpu.tick().unwrap();
+    }
+    let state = cpu.save_state();
+    let screen = cpu.display.clone();
+
+    // Keep running, then rewind to the saved state
+    for _ in 0..30 {
+        cpu.tick().unwrap();
+    }
+    assert_ne!(cpu.display, screen);
+    cpu.load_state(&state).unwrap();
+    assert_eq!(cpu.display, screen);
+    assert_eq!(cpu.save_state(), state);
+}
+
+#[test]
+fn state_from_other_rom_is_refused() {
+    let mut cpu = Cpu::new(500);
+    cpu.load_bytes(&assemble(PROGRAM).unwrap()).unwrap();
+    let state = cpu.save_state();
+
+    cpu.load_bytes(&assemble("CLS").unwrap()).unwrap();
+    assert!(matches!(
+        cpu.load_state(&state),
+        Err(StateError::RomMismatch { .. })
+    ));
+    assert_eq!(cpu.load_state(b"nope"), Err(StateError::BadMagic));
+    assert_eq!(cpu.load_state(&state[..10]), Err(StateError::Truncated));
+}
+
+#[test]
+fn seeded_runs_are_identical() {
+    // Draws the font digit of a random number at random coordinates
+    let rom = assemble(
+        "
+        loop: RND V0, 0x3F
+              RND V1, 0x1F
+              RND V2, 0x0F
+              LD F, V2
+              DRW V0, V1, 5
+              JP loop
+        ",
+    )
+    .unwrap();
+    let run = |seed| {
+        let mut cpu = Cpu::builder(500).seed(seed).build();
+        cpu.load_bytes(&rom).unwrap();
+        for _ in 0..600 {
+            cpu.tick().unwrap();
+        }
+        cpu.display
+    };
+    assert_eq!(run(42), run(42));
+    assert_ne!(run(42), run(43));
+}
@@ -0,0 +1,102 @@
+use chip8::{Cpu, Instruction, Quirks, Register};
+
+fn cpu() -> Cpu {
+    Cpu::builder(500).seed(0).build()
+}
+
+// Returns the lit pixels of the screen as (x, y) pairs
+fn lit(cpu: &Cpu) -> Vec<(usize, usize)> {
+    let display = &cpu.display;
+    (0..display.height())
+        .flat_map(|y| (0..display.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| display.pixel(x, y))
+        .collect()
+}
+
+// Lights a single pixel at (x, y)
+fn plot(cpu: &mut Cpu, x: u8, y: u8) {
+    cpu.write_memory(0x300, &[0x80]).unwrap();
+    cpu.set_register(Register::I, 0x300);
+    cpu.set_register(Register::V(1), x as u16);
+    cpu.set_register(Register::V(2), y as u16);
+    cpu.execute(Instruction::Drw { x: 1, y: 2, n: 1 }).unwrap();
+}
+
+#[test]
+fn resolution_switch_clears_and_resizes() {
+    let mut cpu = cpu();
+    plot(&mut cpu, 5, 5);
+    cpu.execute(Instruction::Hires).unwrap();
+    assert_eq!((cpu.display.width(), cpu.display.height()), (128, 64));
+    assert!(lit(&cpu).is_empty());
+    plot(&mut cpu, 100, 60);
+    assert_eq!(lit(&cpu), [(100, 60)]);
+    cpu.execute(Instruction::Lores).unwrap();
+    assert_eq!((cpu.display.width(), cpu.display.height()), (64, 32));
+    assert!(lit(&cpu).is_empty());
+}
+
+#[test]
+fn scrolling_moves_pixels_and_drops_them_at_the_edges() {
+    let mut cpu = cpu();
+    cpu.execute(Instruction::Hires).unwrap();
+    plot(&mut cpu, 10, 10);
+    cpu.execute(Instruction::ScrollDown(3)).unwrap();
+    assert_eq!(lit(&cpu), [(10, 13)]);
+    cpu.execute(Instruction::ScrollUp(2)).unwrap();
+    assert_eq!(lit(&cpu), [(10, 11)]);
+    cpu.execute(Instruction::ScrollRight).unwrap();
+    assert_eq!(lit(&cpu), [(14, 11)]);
+    cpu.execute(Instruction::ScrollLeft).unwrap();
+    cpu.execute(Instruction::ScrollLeft).unwrap();
+    assert_eq!(lit(&cpu), [(6, 11)]);
+    cpu.execute(Instruction::ScrollLeft).unwrap();
+    cpu.execute(Instruction::ScrollLeft).unwrap();
+    assert!(lit(&cpu).is_empty());
+}
+
+#[test]
+fn dxy0_draws_16x16_sprites() {
+    let mut cpu = cpu();
+    cpu.execute(Instruction::Hires).unwrap();
+    // A hollow square, two bytes per row
+    let mut sprite = vec![0xFF, 0xFF];
+    for _ in 0..14 {
+        sprite.extend_from_slice(&[0x80, 0x01]);
+    }
+    sprite.extend_from_slice(&[0xFF, 0xFF]);
+    cpu.write_memory(0x300, &sprite).unwrap();
+    cpu.set_register(Register::I, 0x300);
+    cpu.set_register(Register::V(1), 8);
+    cpu.set_register(Register::V(2), 4);
+    cpu.execute(Instruction::Drw { x: 1, y: 2, n: 0 }).unwrap();
+    let pixels = lit(&cpu);
+    assert_eq!(pixels.len(), 16 * 2 + 14 * 2);
+    assert!(pixels.contains(&(8, 4)) && pixels.contains(&(23, 19)));
+    assert!(!pixels.contains(&(9, 5)));
+    assert_eq!(cpu.registers()[0xF], 0);
+    assert_eq!(cpu.last_sprite(), Some(0x300..0x320));
+
+    // Drawing it again erases it and reports the collision
+    cpu.execute(Instruction::Drw { x: 1, y: 2, n: 0 }).unwrap();
+    assert!(lit(&cpu).is_empty());
+    assert_eq!(cpu.registers()[0xF], 1);
+}
+
+#[test]
+fn clipped_rows_are_not_read() {
+    // A sprite at the bottom row whose later rows lie past the end of RAM
+    let run = |clip_sprites: bool| {
+        let quirks = Quirks {
+            clip_sprites,
+            ..Quirks::default()
+        };
+        let mut cpu = Cpu::builder(500).seed(0).quirks(quirks).build();
+        let last = cpu.memory().len() as u16 - 1;
+        cpu.set_register(Register::I, last);
+        cpu.set_register(Register::V(2), 31);
+        cpu.execute(Instruction::Drw { x: 1, y: 2, n: 3 })
+    };
+    assert!(run(true).is_ok());
+    assert!(run(false).is_err());
+}
@@ -0,0 +1,112 @@
+// Execution traces are only built with the debug feature
+#![cfg(feature = "debug")]
+use chip8::asm::assemble;
+use chip8::trace::read_binary;
+use chip8::{Cpu, TraceError, TraceFilter, Tracer};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+const PROGRAM: &str = "
+          LD I, 0x300
+    loop: ADD V3, 1
+          LD B, V3
+          CALL sub
+          JP loop
+    sub:  LD V0, V3
+          RET
+";
+
+fn cpu(tracer: Tracer) -> Cpu {
+    let mut cpu = Cpu::builder(600).seed(0).build();
+    cpu.load_bytes(&assemble(PROGRAM).unwrap()).unwrap();
+    cpu.tracer = Some(tracer);
+    cpu
+}
+
+// A writer that can be read back after it has been given to the tracer
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn ring_buffer_keeps_recent_instructions_with_effects() {
+    let mut cpu = cpu(Tracer::ring(4));
+    for _ in 0..6 {
+        cpu.tick().unwrap();
+    }
+    let lines: Vec<String> = cpu
+        .tracer
+        .as_ref()
+        .unwrap()
+        .entries()
+        .map(|entry| entry.to_string())
+        .collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(
+        lines[0],
+        "       2 0204 F333 LD B, V3             I=0300 [0300]=00 [0301]=00 [0302]=01"
+    );
+    assert_eq!(
+        lines[2],
+        "       4 020A 8030 LD V0, V3            V0=00->01 I=0300"
+    );
+    assert!(lines[3].starts_with("       5 020C 00EE RET"));
+}
+
+#[test]
+fn filters_select_addresses_and_mnemonics() {
+    let filter = TraceFilter {
+        range: Some((0x200, 0x20A)),
+        mnemonics: vec!["add".to_string(), "ret".to_string()],
+    };
+    let mut cpu = cpu(Tracer::ring(16).with_filter(filter));
+    for _ in 0..13 {
+        cpu.tick().unwrap();
+    }
+    let traced: Vec<u16> = cpu
+        .tracer
+        .unwrap()
+        .entries()
+        .map(|entry| entry.pc)
+        .collect();
+    assert_eq!(traced, [0x202, 0x202]);
+}
+
+#[test]
+fn binary_trace_reads_back_like_text_trace() {
+    let text = Shared::default();
+    let binary = Shared::default();
+    let mut text_cpu = cpu(Tracer::text(text.clone()));
+    let mut binary_cpu = cpu(Tracer::binary(binary.clone()).unwrap());
+    for _ in 0..100 {
+        text_cpu.tick().unwrap();
+        binary_cpu.tick().unwrap();
+    }
+    text_cpu.tracer.unwrap().finish().unwrap();
+    binary_cpu.tracer.unwrap().finish().unwrap();
+
+    let text = String::from_utf8(text.0.lock().unwrap().clone()).unwrap();
+    let binary = binary.0.lock().unwrap().clone();
+    let entries = read_binary(&binary).unwrap();
+    assert_eq!(entries.len(), 100);
+    let lines: Vec<String> = entries.iter().map(|entry| entry.to_string()).collect();
+    assert_eq!(text.lines().collect::<Vec<_>>(), lines);
+    assert!(binary.len() < text.len() / 2);
+
+    let error = read_binary(b"C8ST").unwrap_err();
+    assert_eq!(error, TraceError::BadMagic);
+    assert_eq!(error.to_string(), "Not a CHIP-8 binary trace");
+    assert_eq!(
+        read_binary(&binary[..binary.len() - 1]),
+        Err(TraceError::Truncated)
+    );
+}